@@ -1,8 +1,11 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Attribute, Data, DataStruct, DeriveInput, Error, Ident, Lit, Meta};
+use syn::{
+    parse_macro_input, Attribute, Data, DataStruct, DeriveInput, Error, Ident, Lit, Meta,
+    NestedMeta, Type,
+};
 
-#[proc_macro_derive(SettingGroup, attributes(setting_prefix))]
+#[proc_macro_derive(SettingGroup, attributes(setting_prefix, setting))]
 pub fn setting_group(item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as DeriveInput);
     let prefix = setting_prefix(input.attrs.as_ref())
@@ -24,15 +27,161 @@ fn stream(input: DeriveInput, prefix: String) -> TokenStream {
     }
 }
 
+// Validation declared with #[setting(min = 0.0, max = 1.0, clamp)] or #[setting(allowed("a", "b"))].
+// Values outside the range are rejected, unless clamp is given
+#[derive(Default)]
+struct FieldOptions {
+    min: Option<Lit>,
+    max: Option<Lit>,
+    allowed: Vec<Lit>,
+    clamp: bool,
+}
+
+fn field_options(attrs: &[Attribute]) -> Result<FieldOptions, Error> {
+    let mut options = FieldOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("setting")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "Expected #[setting(...)]")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("min") =>
+                {
+                    options.min = Some(name_value.lit)
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("max") =>
+                {
+                    options.max = Some(name_value.lit)
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("clamp") => {
+                    options.clamp = true
+                }
+                NestedMeta::Meta(Meta::List(allowed)) if allowed.path.is_ident("allowed") => {
+                    for value in allowed.nested {
+                        match value {
+                            NestedMeta::Lit(lit) => options.allowed.push(lit),
+                            other => {
+                                return Err(Error::new_spanned(other, "Expected a literal value"))
+                            }
+                        }
+                    }
+                }
+                other => {
+                    return Err(Error::new_spanned(
+                        other,
+                        "Expected min = .., max = .., clamp or allowed(..)",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn is_float(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path.path.is_ident("f32") || type_path.path.is_ident("f64"),
+        _ => false,
+    }
+}
+
+fn validation_stream(
+    vim_setting_name: &str,
+    options: &FieldOptions,
+    ty: &Type,
+) -> proc_macro2::TokenStream {
+    // NaN compares false against both bounds, so it would pass the range checks below
+    let nan_check = if is_float(ty) {
+        Some(quote! {
+            if new_value.is_nan() {
+                return Err(format!(
+                    "Invalid value for g:neovide_{}: expected a number, but received NaN",
+                    #vim_setting_name
+                ));
+            }
+        })
+    } else {
+        None
+    };
+
+    let bound_check = |bound: &Lit, out_of_range: proc_macro2::TokenStream, description: &str| {
+        if options.clamp {
+            quote! {
+                if #out_of_range {
+                    new_value = #bound;
+                }
+            }
+        } else {
+            quote! {
+                if #out_of_range {
+                    return Err(format!(
+                        "Invalid value for g:neovide_{}: expected {} {}, but received {}",
+                        #vim_setting_name, #description, #bound, new_value
+                    ));
+                }
+            }
+        }
+    };
+
+    let min_check = options
+        .min
+        .as_ref()
+        .map(|min| bound_check(min, quote!(new_value < #min), "at least"));
+    let max_check = options
+        .max
+        .as_ref()
+        .map(|max| bound_check(max, quote!(new_value > #max), "at most"));
+
+    let allowed_check = if options.allowed.is_empty() {
+        None
+    } else {
+        let allowed = &options.allowed;
+        let allowed_text = allowed
+            .iter()
+            .map(|lit| quote!(#lit).to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        Some(quote! {
+            if ![#(#allowed),*].iter().any(|allowed| new_value == *allowed) {
+                return Err(format!(
+                    "Invalid value for g:neovide_{}: expected one of {}, but received {:?}",
+                    #vim_setting_name, #allowed_text, new_value
+                ));
+            }
+        })
+    };
+
+    quote! {
+        #nan_check
+        #min_check
+        #max_check
+        #allowed_check
+    }
+}
+
 fn struct_stream(name: Ident, prefix: String, data: &DataStruct) -> TokenStream {
     let fragments = data.fields.iter().map(|field| match field.ident {
         Some(ref ident) => {
             let vim_setting_name = format!("{}{}", prefix, ident);
+            let validation = match field_options(&field.attrs) {
+                Ok(options) => validation_stream(&vim_setting_name, &options, &field.ty),
+                Err(error) => return error.to_compile_error(),
+            };
             quote! {{
-                fn update_func(value: rmpv::Value) {
+                fn update_func(value: rmpv::Value) -> Result<(), String> {
                     let mut s = crate::settings::SETTINGS.get::<#name>();
-                    s.#ident.parse_from_value(value);
+                    let mut new_value = s.#ident.clone();
+                    new_value.parse_from_value(value).map_err(|error| {
+                        format!("Invalid value for g:neovide_{}: {}", #vim_setting_name, error)
+                    })?;
+                    #validation
+                    s.#ident = new_value;
                     crate::settings::SETTINGS.set(&s);
+                    Ok(())
                 }
 
                 fn reader_func() -> rmpv::Value {
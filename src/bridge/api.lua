@@ -0,0 +1,32 @@
+-- Installed by Neovide at startup as require('neovide'). Every function is an rpcrequest to
+-- Neovide, so failures are raised as errors in the caller.
+local channel = ...
+
+local function request(method, ...)
+  return vim.rpcrequest(channel, 'neovide.' .. method, ...)
+end
+
+package.loaded.neovide = {
+  channel = channel,
+  toggle_fullscreen = function()
+    return request('toggle_fullscreen')
+  end,
+  set_scale = function(scale)
+    return request('set_scale', scale)
+  end,
+  screenshot = function(path)
+    return request('screenshot', vim.fn.expand(path))
+  end,
+  cell_size = function()
+    return request('cell_size')
+  end,
+  window_size = function()
+    return request('window_size')
+  end,
+  list_fonts = function()
+    return request('list_fonts')
+  end,
+  metrics = function()
+    return request('metrics')
+  end,
+}
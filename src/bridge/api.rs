@@ -0,0 +1,371 @@
+// The rpc surface Neovide exposes to neovim. Requests are made with
+// `rpcrequest(channel, 'neovide.<method>', ...)`, usually through the `neovide` lua module
+// installed at startup:
+//
+//   neovide.get_clipboard(register)  lines and register type of the system clipboard
+//   neovide.toggle_fullscreen()      switches g:neovide_fullscreen
+//   neovide.set_scale(scale)         sets g:neovide_scale_factor, multiplying the os scale factor
+//   neovide.screenshot(path)         writes the last drawn frame to a png
+//   neovide.cell_size()              {width, height} of a grid cell in pixels
+//   neovide.window_size()            {width, height} of the window in pixels
+//   neovide.list_fonts()             names of the fonts installed on the system
+//   neovide.metrics()                g:neovide_metrics, plus the pixel region of every grid
+//
+// Gui events fire `User Neovide*` autocommands, with their details in g:neovide_event:
+//
+//   NeovideResized              {columns, rows, width, height}
+//   NeovideScaleFactorChanged   {scale_factor}
+//   NeovideFontChanged          {font}
+//   NeovideFullscreenChanged    {fullscreen}
+//   NeovideFileDropped          {path}
+//
+// There are no input method events, as the window system only reports text committed by an input
+// method, not when composing starts, is cancelled or ends.
+use std::{fmt, path::PathBuf, sync::Arc};
+
+use parking_lot::Mutex;
+use rmpv::Value;
+use tokio::sync::oneshot;
+
+// Installs `require('neovide')`, called with the channel id of Neovide as its only argument
+pub const LUA_MODULE: &str = include_str!("api.lua");
+
+pub type ApiResult = Result<Value, String>;
+
+// Requests which need the state of the window, and so are answered from the window thread
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowRequest {
+    Screenshot(PathBuf),
+    CellSize,
+    WindowSize,
+    ListFonts,
+    Metrics,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiRequest {
+    GetClipboard { register: String },
+    ToggleFullscreen,
+    SetScale(f64),
+    Window(WindowRequest),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiNotification {
+    Redraw(Vec<Value>),
+    SettingChanged(Vec<Value>),
+    Quit(i64),
+    SetClipboard(Vec<Value>),
+    #[cfg(windows)]
+    RegisterRightClick,
+    #[cfg(windows)]
+    UnregisterRightClick,
+}
+
+fn argument<'a>(method: &str, arguments: &'a [Value], index: usize) -> Result<&'a Value, String> {
+    arguments
+        .get(index)
+        .ok_or_else(|| format!("{} expects at least {} arguments", method, index + 1))
+}
+
+impl ApiRequest {
+    pub fn parse(method: &str, arguments: &[Value]) -> Result<ApiRequest, String> {
+        match method {
+            "neovide.get_clipboard" => Ok(ApiRequest::GetClipboard {
+                register: arguments
+                    .get(0)
+                    .and_then(Value::as_str)
+                    .unwrap_or("+")
+                    .to_owned(),
+            }),
+            "neovide.toggle_fullscreen" => Ok(ApiRequest::ToggleFullscreen),
+            "neovide.set_scale" => {
+                let scale = argument(method, arguments, 0)?;
+                match scale
+                    .as_f64()
+                    .or_else(|| scale.as_i64().map(|scale| scale as f64))
+                {
+                    Some(scale) if scale > 0.0 => Ok(ApiRequest::SetScale(scale)),
+                    _ => Err(format!(
+                        "{} expects a positive number, got {}",
+                        method, scale
+                    )),
+                }
+            }
+            "neovide.screenshot" => argument(method, arguments, 0)?
+                .as_str()
+                .map(|path| ApiRequest::Window(WindowRequest::Screenshot(PathBuf::from(path))))
+                .ok_or_else(|| format!("{} expects a file path", method)),
+            "neovide.cell_size" => Ok(ApiRequest::Window(WindowRequest::CellSize)),
+            "neovide.window_size" => Ok(ApiRequest::Window(WindowRequest::WindowSize)),
+            "neovide.list_fonts" => Ok(ApiRequest::Window(WindowRequest::ListFonts)),
+            "neovide.metrics" => Ok(ApiRequest::Window(WindowRequest::Metrics)),
+            _ => Err(format!("Unknown request {}", method)),
+        }
+    }
+}
+
+impl ApiNotification {
+    // Unknown notifications are ignored, as other clients may share the channel's event names
+    pub fn parse(method: &str, arguments: Vec<Value>) -> Option<Result<ApiNotification, String>> {
+        let notification = match method {
+            "redraw" => Ok(ApiNotification::Redraw(arguments)),
+            "setting_changed" => Ok(ApiNotification::SettingChanged(arguments)),
+            "neovide.quit" => arguments
+                .get(0)
+                .and_then(Value::as_i64)
+                .map(ApiNotification::Quit)
+                .ok_or_else(|| "Could not parse error code from neovim".to_owned()),
+            "neovide.set_clipboard" => Ok(ApiNotification::SetClipboard(arguments)),
+            #[cfg(windows)]
+            "neovide.register_right_click" => Ok(ApiNotification::RegisterRightClick),
+            #[cfg(windows)]
+            "neovide.unregister_right_click" => Ok(ApiNotification::UnregisterRightClick),
+            _ => return None,
+        };
+        Some(notification)
+    }
+}
+
+// Answers a request from the thread that handles it. The sender is shared so the responder can be
+// sent through the event aggregator, which requires events to be Clone
+#[derive(Clone)]
+pub struct ApiResponder(Arc<Mutex<Option<oneshot::Sender<ApiResult>>>>);
+
+impl ApiResponder {
+    pub fn new() -> (ApiResponder, oneshot::Receiver<ApiResult>) {
+        let (sender, receiver) = oneshot::channel();
+        (ApiResponder(Arc::new(Mutex::new(Some(sender)))), receiver)
+    }
+
+    pub fn respond(&self, result: ApiResult) {
+        if let Some(sender) = self.0.lock().take() {
+            sender.send(result).ok();
+        }
+    }
+}
+
+impl fmt::Debug for ApiResponder {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("ApiResponder")
+    }
+}
+
+// {width = .., height = ..} as returned for sizes
+pub fn size_value(width: u64, height: u64) -> Value {
+    Value::Map(vec![
+        (Value::from("width"), Value::from(width)),
+        (Value::from("height"), Value::from(height)),
+    ])
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GuiEvent {
+    Resized {
+        columns: u64,
+        rows: u64,
+        width: u64,
+        height: u64,
+    },
+    ScaleFactorChanged(f64),
+    FontChanged(String),
+    FullscreenChanged(bool),
+    FileDropped(String),
+}
+
+impl GuiEvent {
+    pub fn autocmd_pattern(&self) -> &'static str {
+        match self {
+            GuiEvent::Resized { .. } => "NeovideResized",
+            GuiEvent::ScaleFactorChanged(_) => "NeovideScaleFactorChanged",
+            GuiEvent::FontChanged(_) => "NeovideFontChanged",
+            GuiEvent::FullscreenChanged(_) => "NeovideFullscreenChanged",
+            GuiEvent::FileDropped(_) => "NeovideFileDropped",
+        }
+    }
+
+    pub fn details(&self) -> Value {
+        let entries: Vec<(&str, Value)> = match self {
+            GuiEvent::Resized {
+                columns,
+                rows,
+                width,
+                height,
+            } => vec![
+                ("columns", Value::from(*columns)),
+                ("rows", Value::from(*rows)),
+                ("width", Value::from(*width)),
+                ("height", Value::from(*height)),
+            ],
+            GuiEvent::ScaleFactorChanged(scale_factor) => {
+                vec![("scale_factor", Value::from(*scale_factor))]
+            }
+            GuiEvent::FontChanged(font) => vec![("font", Value::from(font.as_str()))],
+            GuiEvent::FullscreenChanged(fullscreen) => {
+                vec![("fullscreen", Value::from(*fullscreen))]
+            }
+            GuiEvent::FileDropped(path) => vec![("path", Value::from(path.as_str()))],
+        };
+
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Value::from(key), value))
+                .collect(),
+        )
+    }
+}
+
+// Pixel sizes only the gui knows, published to g:neovide_metrics for image preview and layout
+// plugins. Scale factor includes the scale_factor setting
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuiMetrics {
+    pub cell_width: u64,
+    pub cell_height: u64,
+    pub scale_factor: f64,
+    pub window_width: u64,
+    pub window_height: u64,
+    pub grid_top: u64,
+}
+
+// Where a grid is drawn in the window, in pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridRegion {
+    pub grid: u64,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl GuiMetrics {
+    pub fn to_value(&self) -> Value {
+        Value::Map(vec![
+            (Value::from("cell_width"), Value::from(self.cell_width)),
+            (Value::from("cell_height"), Value::from(self.cell_height)),
+            (Value::from("scale_factor"), Value::from(self.scale_factor)),
+            (Value::from("window_width"), Value::from(self.window_width)),
+            (
+                Value::from("window_height"),
+                Value::from(self.window_height),
+            ),
+            (Value::from("grid_top"), Value::from(self.grid_top)),
+        ])
+    }
+}
+
+impl GridRegion {
+    pub fn to_value(&self) -> Value {
+        Value::Map(vec![
+            (Value::from("grid"), Value::from(self.grid)),
+            (Value::from("x"), Value::from(self.x)),
+            (Value::from("y"), Value::from(self.y)),
+            (Value::from("width"), Value::from(self.width)),
+            (Value::from("height"), Value::from(self.height)),
+        ])
+    }
+}
+
+// The metrics with a grids entry, as answered to neovide.metrics
+pub fn metrics_value(metrics: &GuiMetrics, grids: &[GridRegion]) -> Value {
+    let mut value = metrics.to_value();
+    if let Value::Map(entries) = &mut value {
+        entries.push((
+            Value::from("grids"),
+            Value::from(
+                grids
+                    .iter()
+                    .map(GridRegion::to_value)
+                    .collect::<Vec<Value>>(),
+            ),
+        ));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            ApiRequest::parse("neovide.set_scale", &[Value::from(1.5)]),
+            Ok(ApiRequest::SetScale(1.5))
+        );
+        assert_eq!(
+            ApiRequest::parse("neovide.set_scale", &[Value::from(2)]),
+            Ok(ApiRequest::SetScale(2.0))
+        );
+        assert!(ApiRequest::parse("neovide.set_scale", &[Value::from(-1.0)]).is_err());
+        assert!(ApiRequest::parse("neovide.set_scale", &[]).is_err());
+        assert_eq!(
+            ApiRequest::parse("neovide.screenshot", &[Value::from("/tmp/frame.png")]),
+            Ok(ApiRequest::Window(WindowRequest::Screenshot(
+                PathBuf::from("/tmp/frame.png")
+            )))
+        );
+        assert_eq!(
+            ApiRequest::parse("neovide.get_clipboard", &[]),
+            Ok(ApiRequest::GetClipboard {
+                register: "+".to_owned()
+            })
+        );
+        assert!(ApiRequest::parse("neovide.unknown", &[]).is_err());
+    }
+
+    #[test]
+    fn test_gui_event() {
+        let event = GuiEvent::FullscreenChanged(true);
+        assert_eq!(event.autocmd_pattern(), "NeovideFullscreenChanged");
+        assert_eq!(
+            event.details(),
+            Value::Map(vec![(Value::from("fullscreen"), Value::from(true))])
+        );
+    }
+
+    #[test]
+    fn test_metrics_value() {
+        let metrics = GuiMetrics {
+            cell_width: 10,
+            cell_height: 20,
+            scale_factor: 2.0,
+            window_width: 800,
+            window_height: 600,
+            grid_top: 0,
+        };
+        let grids = [GridRegion {
+            grid: 2,
+            x: 0.0,
+            y: 20.0,
+            width: 400.0,
+            height: 560.0,
+        }];
+
+        let value = metrics_value(&metrics, &grids);
+        let entries = value.as_map().unwrap();
+        let entry = |key: &str| {
+            entries
+                .iter()
+                .find(|(name, _)| name.as_str() == Some(key))
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(entry("cell_height"), Some(Value::from(20)));
+        assert_eq!(entry("scale_factor"), Some(Value::from(2.0)));
+        let grids = entry("grids").unwrap();
+        assert_eq!(grids.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_notification() {
+        assert_eq!(
+            ApiNotification::parse("neovide.quit", vec![Value::from(3)]),
+            Some(Ok(ApiNotification::Quit(3)))
+        );
+        assert!(matches!(
+            ApiNotification::parse("neovide.quit", vec![]),
+            Some(Err(_))
+        ));
+        assert_eq!(ApiNotification::parse("other_plugin.event", vec![]), None);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use rmpv::Value;
+
+use crate::bridge::ParseError;
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NeovimVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+// The features of the attached neovim, as reported by nvim_get_api_info
+#[derive(Clone, Debug, Default)]
+pub struct NeovimCapabilities {
+    pub channel: u64,
+    pub version: NeovimVersion,
+    pub api_level: u64,
+    pub ui_options: HashSet<String>,
+}
+
+fn parse_u64(value: &Value) -> Result<u64> {
+    value.as_u64().ok_or_else(|| ParseError::U64(value.clone()))
+}
+
+fn parse_map(value: &Value) -> Result<&Vec<(Value, Value)>> {
+    value.as_map().ok_or_else(|| ParseError::Map(value.clone()))
+}
+
+fn parse_array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| ParseError::Array(value.clone()))
+}
+
+fn map_entry<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(name, _)| name.as_str() == Some(key))
+        .map(|(_, value)| value)
+}
+
+impl NeovimCapabilities {
+    pub fn parse(api_info: Vec<Value>) -> Result<NeovimCapabilities> {
+        let (channel, metadata) = match api_info.as_slice() {
+            [channel, metadata, ..] => (parse_u64(channel)?, parse_map(metadata)?),
+            _ => return Err(ParseError::Format(format!("{:?}", api_info))),
+        };
+
+        let mut capabilities = NeovimCapabilities {
+            channel,
+            ..Default::default()
+        };
+
+        if let Some(version) = map_entry(metadata, "version") {
+            let version = parse_map(version)?;
+            let field = |name| map_entry(version, name).map(parse_u64).transpose();
+            capabilities.version = NeovimVersion {
+                major: field("major")?.unwrap_or(0),
+                minor: field("minor")?.unwrap_or(0),
+                patch: field("patch")?.unwrap_or(0),
+            };
+            capabilities.api_level = field("api_level")?.unwrap_or(0);
+        }
+
+        if let Some(ui_options) = map_entry(metadata, "ui_options") {
+            capabilities.ui_options = parse_array(ui_options)?
+                .iter()
+                .filter_map(|option| option.as_str().map(String::from))
+                .collect();
+        }
+
+        Ok(capabilities)
+    }
+
+    pub fn has_version(&self, major: u64, minor: u64) -> bool {
+        (self.version.major, self.version.minor) >= (major, minor)
+    }
+
+    pub fn has_ui_option(&self, option: &str) -> bool {
+        self.ui_options.contains(option)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_info() -> Vec<Value> {
+        let version = Value::Map(vec![
+            (Value::from("major"), Value::from(0)),
+            (Value::from("minor"), Value::from(7)),
+            (Value::from("patch"), Value::from(2)),
+            (Value::from("api_level"), Value::from(9)),
+        ]);
+        let ui_options = Value::Array(vec![
+            Value::from("rgb"),
+            Value::from("ext_linegrid"),
+            Value::from("ext_multigrid"),
+        ]);
+
+        vec![
+            Value::from(3),
+            Value::Map(vec![
+                (Value::from("version"), version),
+                (Value::from("ui_options"), ui_options),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn test_parse_capabilities() {
+        let capabilities = NeovimCapabilities::parse(api_info()).unwrap();
+
+        assert_eq!(capabilities.channel, 3);
+        assert_eq!(
+            capabilities.version,
+            NeovimVersion {
+                major: 0,
+                minor: 7,
+                patch: 2
+            }
+        );
+        assert_eq!(capabilities.api_level, 9);
+        assert!(capabilities.has_version(0, 4));
+        assert!(!capabilities.has_version(0, 8));
+        assert!(capabilities.has_ui_option("ext_multigrid"));
+        assert!(!capabilities.has_ui_option("ext_popupmenu"));
+    }
+
+    #[test]
+    fn test_parse_capabilities_invalid() {
+        assert!(NeovimCapabilities::parse(vec![Value::from("foo")]).is_err());
+    }
+}
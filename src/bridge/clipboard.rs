@@ -1,12 +1,77 @@
-use std::error::Error;
+use std::{collections::HashMap, error::Error, sync::Mutex};
 
 use rmpv::Value;
 
 use arboard::Clipboard;
+#[cfg(target_os = "linux")]
+use arboard::{ClipboardExtLinux, LinuxClipboardKind};
 
-pub fn get_remote_clipboard(format: Option<&str>) -> Result<Value, Box<dyn Error>> {
+lazy_static! {
+    // The system clipboard only holds text, so the register type of the last copy to each register
+    // is kept along with that text. It is only used while the clipboard still holds the same text
+    static ref REGISTER_TYPES: Mutex<HashMap<String, (String, String)>> =
+        Mutex::new(HashMap::new());
+}
+
+fn remember_regtype(register: &str, text: &str, regtype: &str) {
+    REGISTER_TYPES
+        .lock()
+        .unwrap()
+        .insert(register.to_owned(), (text.to_owned(), regtype.to_owned()));
+}
+
+fn regtype_for(register: &str, text: &str) -> String {
+    match REGISTER_TYPES.lock().unwrap().get(register) {
+        Some((copied_text, regtype)) if copied_text == text => regtype.clone(),
+        // v paste is normal paste (everything in lines is pasted)
+        _ => "v".to_owned(),
+    }
+}
+
+// On Linux '*' is the primary selection, like in a local neovim. Elsewhere both registers share
+// the one clipboard
+#[cfg(target_os = "linux")]
+fn get_text(clipboard_ctx: &mut Clipboard, register: &str) -> Result<String, arboard::Error> {
+    let kind = if register == "*" {
+        LinuxClipboardKind::Primary
+    } else {
+        LinuxClipboardKind::Clipboard
+    };
+    clipboard_ctx.get_text_with_clipboard(kind)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_text(clipboard_ctx: &mut Clipboard, _register: &str) -> Result<String, arboard::Error> {
+    clipboard_ctx.get_text()
+}
+
+#[cfg(target_os = "linux")]
+fn set_text(
+    clipboard_ctx: &mut Clipboard,
+    register: &str,
+    text: String,
+) -> Result<(), arboard::Error> {
+    let kind = if register == "*" {
+        LinuxClipboardKind::Primary
+    } else {
+        LinuxClipboardKind::Clipboard
+    };
+    clipboard_ctx.set_text_with_clipboard(text, kind)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_text(
+    clipboard_ctx: &mut Clipboard,
+    _register: &str,
+    text: String,
+) -> Result<(), arboard::Error> {
+    clipboard_ctx.set_text(text)
+}
+
+pub fn get_remote_clipboard(format: Option<&str>, register: &str) -> Result<Value, Box<dyn Error>> {
     let mut clipboard_ctx = Clipboard::new()?;
-    let clipboard_raw = clipboard_ctx.get_text()?.replace('\r', "");
+    let clipboard_raw = get_text(&mut clipboard_ctx, register)?.replace('\r', "");
+    let paste_mode = Value::from(regtype_for(register, &clipboard_raw));
 
     let lines = if let Some("dos") = format {
         // add \r to lines of current file format is dos
@@ -20,12 +85,8 @@ pub fn get_remote_clipboard(format: Option<&str>) -> Result<Value, Box<dyn Error
     .collect::<Vec<Value>>();
 
     let lines = Value::from(lines);
-    // v paste is normal paste (everything in lines is pasted)
-    // V paste is paste with extra endline (line paste)
-    // If you want V paste, copy text with extra endline
-    let paste_mode = Value::from("v");
 
-    // returns [content: [String], paste_mode: v or V]
+    // returns [content: [String], paste_mode: v, V or a blockwise type]
     Ok(Value::from(vec![lines, paste_mode]))
 }
 
@@ -46,11 +107,31 @@ pub fn set_remote_clipboard(arguments: Vec<Value>) -> Result<(), Box<dyn Error>>
                 .filter_map(|x| x.as_str().map(String::from))
                 .map(|s| s.replace('\r', "")) // strip \r
                 .collect::<Vec<String>>()
-                .join(endline)
         })
         .ok_or("can't build string from provided text")?;
+    let regtype = arguments[1].as_str().unwrap_or("v");
+    let register = arguments[2].as_str().unwrap_or("+");
+
+    remember_regtype(register, &lines.join("\n"), regtype);
 
     let mut clipboard_ctx = Clipboard::new()?;
-    clipboard_ctx.set_text(lines)?;
+    set_text(&mut clipboard_ctx, register, lines.join(endline))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regtype_for() {
+        remember_regtype("+", "first\nsecond", "V");
+        remember_regtype("*", "abc\ndef", "\u{16}3");
+
+        assert_eq!(regtype_for("+", "first\nsecond"), "V");
+        assert_eq!(regtype_for("*", "abc\ndef"), "\u{16}3");
+        // The clipboard was changed by another application since the copy
+        assert_eq!(regtype_for("+", "something else"), "v");
+        assert_eq!(regtype_for("a", "first\nsecond"), "v");
+    }
+}
@@ -32,6 +32,12 @@ fn set_windows_creation_flags(cmd: &mut TokioCommand) {
 }
 
 fn build_nvim_cmd() -> TokioCommand {
+    let settings = SETTINGS.get::<CmdLineSettings>();
+    if let Some(host) = settings.ssh {
+        // The binary is resolved by the remote shell, so it can't be probed locally
+        let bin = settings.neovim_bin.unwrap_or_else(|| "nvim".to_owned());
+        return build_ssh_cmd(&host, &bin, &settings.neovim_args);
+    }
     if let Some(path) = SETTINGS.get::<CmdLineSettings>().neovim_bin {
         if platform_exists(&path) {
             return build_nvim_cmd_with_args(&path);
@@ -47,12 +53,40 @@ fn build_nvim_cmd() -> TokioCommand {
     }
 }
 
-// Creates a shell command if needed on this platform (wsl or macos)
+// Returns the command prefix used to run neovim inside another environment, such as WSL or a
+// container. The command run inside of it is appended as a single shell command line.
+fn exec_wrapper(settings: &CmdLineSettings) -> Option<Vec<String>> {
+    if let Some(wrapper) = &settings.exec_wrapper {
+        let mut prefix = match shell_words::split(wrapper) {
+            Ok(prefix) if !prefix.is_empty() => prefix,
+            Ok(_) => {
+                error!("--exec-wrapper is empty");
+                std::process::exit(1);
+            }
+            Err(error) => {
+                error!("Could not parse --exec-wrapper {:?}: {}", wrapper, error);
+                std::process::exit(1);
+            }
+        };
+        prefix.extend(["sh".to_owned(), "-lc".to_owned()]);
+        Some(prefix)
+    } else if cfg!(target_os = "windows") && settings.wsl {
+        Some(vec![
+            "wsl".to_owned(),
+            "$SHELL".to_owned(),
+            "-lc".to_owned(),
+        ])
+    } else {
+        None
+    }
+}
+
+// Creates a shell command if needed on this platform (exec wrapper, wsl or macos)
 fn create_platform_shell_command(command: &str, args: &[&str]) -> Option<StdCommand> {
-    if cfg!(target_os = "windows") && SETTINGS.get::<CmdLineSettings>().wsl {
-        let mut result = StdCommand::new("wsl");
-        result.args(&["$SHELL", "-lc"]);
-        result.arg(format!("{} {}", command, args.join(" ")));
+    if let Some(wrapper) = exec_wrapper(&SETTINGS.get::<CmdLineSettings>()) {
+        let mut result = StdCommand::new(&wrapper[0]);
+        result.args(&wrapper[1..]);
+        result.arg(shell_command_line(command, args));
 
         Some(result)
     } else if cfg!(target_os = "macos") {
@@ -60,7 +94,7 @@ fn create_platform_shell_command(command: &str, args: &[&str]) -> Option<StdComm
         let mut result = StdCommand::new(&shell);
 
         result.args(&["-lc"]);
-        result.arg(format!("{} {}", command, args.join(" ")));
+        result.arg(shell_command_line(command, args));
 
         Some(result)
     } else {
@@ -105,16 +139,17 @@ fn platform_which(bin: &str) -> Option<String> {
 fn build_nvim_cmd_with_args(bin: &str) -> TokioCommand {
     let mut args = vec!["--embed".to_string()];
     args.extend(SETTINGS.get::<CmdLineSettings>().neovim_args);
-    let args_str = args.join(" ");
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
 
-    if cfg!(target_os = "windows") && SETTINGS.get::<CmdLineSettings>().wsl {
-        let mut cmd = TokioCommand::new("wsl");
-        cmd.args(&["$SHELL", "-lc", &format!("{} {}", bin, args_str)]);
+    if let Some(wrapper) = exec_wrapper(&SETTINGS.get::<CmdLineSettings>()) {
+        let mut cmd = TokioCommand::new(&wrapper[0]);
+        cmd.args(&wrapper[1..]);
+        cmd.arg(shell_command_line(bin, &arg_refs));
         cmd
     } else if cfg!(target_os = "macos") {
         let shell = env::var("SHELL").unwrap();
         let mut cmd = TokioCommand::new(shell);
-        cmd.args(&["-lc", &format!("{} {}", bin, args_str)]);
+        cmd.args(&["-lc", &shell_command_line(bin, &arg_refs)]);
         cmd
     } else {
         let mut cmd = TokioCommand::new(bin);
@@ -122,3 +157,97 @@ fn build_nvim_cmd_with_args(bin: &str) -> TokioCommand {
         cmd
     }
 }
+
+fn build_ssh_cmd(host: &str, bin: &str, neovim_args: &[String]) -> TokioCommand {
+    // ssh joins everything after the host into a single command line for the remote shell, so
+    // the arguments need to be quoted to survive that
+    let mut args = vec![shell_quote(bin), "--embed".to_string()];
+    args.extend(neovim_args.iter().map(|arg| shell_quote(arg)));
+
+    let mut cmd = TokioCommand::new("ssh");
+    cmd.arg("-T").arg(host).args(args);
+    cmd
+}
+
+// Builds a command line for `sh -c`, quoting every part so the shell runs it exactly as given
+fn shell_command_line(command: &str, args: &[&str]) -> String {
+    std::iter::once(command)
+        .chain(args.iter().copied())
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_ssh_cmd() {
+        let cmd = build_ssh_cmd(
+            "user@host",
+            "nvim",
+            &["--clean".to_owned(), "some file.txt".to_owned()],
+        );
+        let cmd = cmd.as_std();
+
+        assert_eq!(cmd.get_program(), "ssh");
+        assert_eq!(
+            cmd.get_args().collect::<Vec<_>>(),
+            vec![
+                "-T",
+                "user@host",
+                "nvim",
+                "--embed",
+                "--clean",
+                "'some file.txt'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exec_wrapper() {
+        let settings = CmdLineSettings {
+            exec_wrapper: Some("podman exec -i 'my box'".to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            exec_wrapper(&settings),
+            Some(
+                ["podman", "exec", "-i", "my box", "sh", "-lc"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            )
+        );
+        assert_eq!(exec_wrapper(&CmdLineSettings::default()), None);
+    }
+
+    #[test]
+    fn test_shell_command_line() {
+        assert_eq!(
+            shell_command_line(
+                "/opt/nvim/bin/nvim",
+                &["--embed", "my file.txt", "a;rm -rf ~"]
+            ),
+            "/opt/nvim/bin/nvim --embed 'my file.txt' 'a;rm -rf ~'"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("./foo.txt"), "./foo.txt");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+}
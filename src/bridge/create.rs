@@ -3,10 +3,15 @@
 
 use std::{
     io::{self, Error, ErrorKind},
+    path::Path,
     process::Stdio,
 };
 
 use nvim_rs::{error::LoopError, neovim::Neovim, Handler};
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::ClientOptions;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::{
     io::split,
     net::{TcpStream, ToSocketAddrs},
@@ -35,6 +40,42 @@ where
     Ok((neovim, io_handle))
 }
 
+/// Connect to a neovim instance via a unix domain socket
+#[cfg(unix)]
+pub async fn new_path<P, H>(
+    path: P,
+    handler: H,
+) -> io::Result<(Neovim<TxWrapper>, JoinHandle<Result<(), Box<LoopError>>>)>
+where
+    P: AsRef<Path>,
+    H: Handler<Writer = TxWrapper>,
+{
+    let stream = UnixStream::connect(path).await?;
+    let (reader, writer) = split(stream);
+    let (neovim, io) = Neovim::<TxWrapper>::new(reader.compat(), writer.wrap_tx(), handler);
+    let io_handle = spawn(io);
+
+    Ok((neovim, io_handle))
+}
+
+/// Connect to a neovim instance via a named pipe
+#[cfg(windows)]
+pub async fn new_path<P, H>(
+    path: P,
+    handler: H,
+) -> io::Result<(Neovim<TxWrapper>, JoinHandle<Result<(), Box<LoopError>>>)>
+where
+    P: AsRef<Path>,
+    H: Handler<Writer = TxWrapper>,
+{
+    let stream = ClientOptions::new().open(path.as_ref())?;
+    let (reader, writer) = split(stream);
+    let (neovim, io) = Neovim::<TxWrapper>::new(reader.compat(), writer.wrap_tx(), handler);
+    let io_handle = spawn(io);
+
+    Ok((neovim, io_handle))
+}
+
 /// Connect to a neovim instance by spawning a new one
 ///
 /// stdin/stdout will be rewritten to `Stdio::piped()`
@@ -8,7 +8,7 @@ use log::debug;
 use rmpv::Value;
 use skia_safe::Color4f;
 
-use crate::editor::{Colors, CursorMode, CursorShape, Style};
+use crate::editor::{Colors, CursorMode, CursorShape, MouseShape, Style, UnderlineStyle};
 
 #[derive(Clone, Debug)]
 pub enum ParseError {
@@ -59,6 +59,20 @@ pub struct GridLineCell {
 
 pub type StyledContent = Vec<(u64, String)>;
 
+#[derive(Clone, Debug)]
+pub struct PopupMenuItem {
+    pub word: String,
+    pub kind: String,
+    pub menu: String,
+    pub info: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct TabInfo {
+    pub tab: u64,
+    pub name: String,
+}
+
 #[derive(Clone, Debug)]
 pub enum MessageKind {
     Unknown,
@@ -104,7 +118,7 @@ pub enum GuiOption {
     GuiFont(String),
     GuiFontSet(String),
     GuiFontWide(String),
-    LineSpace(u64),
+    LineSpace(i64),
     Pumblend(u64),
     ShowTabLine(u64),
     TermGuiColors(bool),
@@ -166,6 +180,10 @@ pub enum RedrawEvent {
         id: u64,
         style: Style,
     },
+    HighlightGroupSet {
+        name: String,
+        id: u64,
+    },
     GridLine {
         grid: u64,
         row: u64,
@@ -274,6 +292,21 @@ pub enum RedrawEvent {
     MessageHistoryShow {
         entries: Vec<(MessageKind, StyledContent)>,
     },
+    PopupMenuShow {
+        items: Vec<PopupMenuItem>,
+        selected: Option<u64>,
+        row: u64,
+        column: u64,
+        grid: Option<u64>,
+    },
+    PopupMenuSelect {
+        selected: Option<u64>,
+    },
+    PopupMenuHide,
+    TablineUpdate {
+        current_tab: u64,
+        tabs: Vec<TabInfo>,
+    },
 }
 
 fn unpack_color(packed_color: u64) -> Color4f {
@@ -398,6 +431,9 @@ fn parse_mode_info_set(mode_info_set_arguments: Vec<Value>) -> Result<RedrawEven
                 "attr_id" => {
                     mode_info.style_id = Some(parse_u64(value)?);
                 }
+                "mouse_shape" => {
+                    mode_info.mouse_shape = MouseShape::from_id(parse_u64(value)?);
+                }
                 _ => {}
             }
         }
@@ -421,7 +457,7 @@ fn parse_option_set(option_set_arguments: Vec<Value>) -> Result<RedrawEvent> {
             "guifont" => GuiOption::GuiFont(parse_string(value)?),
             "guifontset" => GuiOption::GuiFontSet(parse_string(value)?),
             "guifontwide" => GuiOption::GuiFontWide(parse_string(value)?),
-            "linespace" => GuiOption::LineSpace(parse_u64(value)?),
+            "linespace" => GuiOption::LineSpace(parse_i64(value)?),
             "pumblend" => GuiOption::Pumblend(parse_u64(value)?),
             "showtabline" => GuiOption::ShowTabLine(parse_u64(value)?),
             "termguicolors" => GuiOption::TermGuiColors(parse_bool(value)?),
@@ -493,9 +529,29 @@ fn parse_style(style_map: Value) -> Result<Style> {
                 ("strikethrough", Value::Boolean(strikethrough)) => {
                     style.strikethrough = strikethrough
                 }
-                ("underline", Value::Boolean(underline)) => style.underline = underline,
-                ("undercurl", Value::Boolean(undercurl)) => style.undercurl = undercurl,
+                ("underline", Value::Boolean(true)) => {
+                    style.underline = Some(UnderlineStyle::Underline)
+                }
+                // Neovim 0.8 renamed underlineline to underdouble
+                ("underdouble", Value::Boolean(true)) | ("underlineline", Value::Boolean(true)) => {
+                    style.underline = Some(UnderlineStyle::UnderDouble)
+                }
+                ("undercurl", Value::Boolean(true)) => {
+                    style.underline = Some(UnderlineStyle::UnderCurl)
+                }
+                ("underdotted", Value::Boolean(true)) => {
+                    style.underline = Some(UnderlineStyle::UnderDotted)
+                }
+                ("underdashed", Value::Boolean(true)) => {
+                    style.underline = Some(UnderlineStyle::UnderDashed)
+                }
+                (
+                    "underline" | "underdouble" | "underlineline" | "undercurl" | "underdotted"
+                    | "underdashed",
+                    Value::Boolean(false),
+                ) => {}
                 ("blend", Value::Integer(blend)) => style.blend = blend.as_u64().unwrap() as u8,
+                ("url", Value::String(url)) => style.url = url.into_str(),
                 _ => debug!("Ignored style attribute: {}", name),
             }
         } else {
@@ -516,6 +572,15 @@ fn parse_hl_attr_define(hl_attr_define_arguments: Vec<Value>) -> Result<RedrawEv
     })
 }
 
+fn parse_hl_group_set(hl_group_set_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [name, id] = extract_values(hl_group_set_arguments)?;
+
+    Ok(RedrawEvent::HighlightGroupSet {
+        name: parse_string(name)?,
+        id: parse_u64(id)?,
+    })
+}
+
 fn parse_grid_line_cell(grid_line_cell: Value) -> Result<GridLineCell> {
     fn take_value(val: &mut Value) -> Value {
         std::mem::replace(val, Value::Nil)
@@ -816,6 +881,84 @@ fn parse_msg_history_show(msg_history_show_arguments: Vec<Value>) -> Result<Redr
     })
 }
 
+// Neovim uses -1 for "nothing", like no selected item or no anchor grid
+fn parse_optional_index(index: Value) -> Result<Option<u64>> {
+    Ok(parse_i64(index)?.try_into().ok())
+}
+
+fn parse_popupmenu_item(item: Value) -> Result<PopupMenuItem> {
+    let [word, kind, menu, info] = extract_values(parse_array(item)?)?;
+
+    Ok(PopupMenuItem {
+        word: parse_string(word)?,
+        kind: parse_string(kind)?,
+        menu: parse_string(menu)?,
+        info: parse_string(info)?,
+    })
+}
+
+fn parse_popupmenu_show(popupmenu_show_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [items, selected, row, column, grid] = extract_values(popupmenu_show_arguments)?;
+
+    Ok(RedrawEvent::PopupMenuShow {
+        items: parse_array(items)?
+            .into_iter()
+            .map(parse_popupmenu_item)
+            .collect::<Result<_>>()?,
+        selected: parse_optional_index(selected)?,
+        row: parse_u64(row)?,
+        column: parse_u64(column)?,
+        grid: parse_optional_index(grid)?,
+    })
+}
+
+fn parse_popupmenu_select(popupmenu_select_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [selected] = extract_values(popupmenu_select_arguments)?;
+
+    Ok(RedrawEvent::PopupMenuSelect {
+        selected: parse_optional_index(selected)?,
+    })
+}
+
+// Tabpages are sent as msgpack extension values wrapping the integer handle
+fn parse_handle(handle: Value) -> Result<u64> {
+    match handle {
+        Value::Ext(_, data) => rmpv::decode::read_value(&mut data.as_slice())
+            .map_err(|error| ParseError::Format(error.to_string()))
+            .and_then(parse_u64),
+        handle => parse_u64(handle),
+    }
+}
+
+fn parse_tab_info(tab_info: Value) -> Result<TabInfo> {
+    let mut tab = None;
+    let mut name = String::new();
+    for (key, value) in parse_map(tab_info)? {
+        match parse_string(key)?.as_str() {
+            "tab" => tab = Some(parse_handle(value)?),
+            "name" => name = parse_string(value)?,
+            _ => {}
+        }
+    }
+
+    Ok(TabInfo {
+        tab: tab.ok_or_else(|| ParseError::Format("tab info without a tab handle".to_owned()))?,
+        name,
+    })
+}
+
+fn parse_tabline_update(tabline_update_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [current_tab, tabs] = extract_values(tabline_update_arguments)?;
+
+    Ok(RedrawEvent::TablineUpdate {
+        current_tab: parse_handle(current_tab)?,
+        tabs: parse_array(tabs)?
+            .into_iter()
+            .map(parse_tab_info)
+            .collect::<Result<_>>()?,
+    })
+}
+
 pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
     let mut event_contents = parse_array(event_value)?.into_iter();
     let event_name = event_contents
@@ -842,6 +985,7 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
             "grid_resize" => Some(parse_grid_resize(event_parameters)?),
             "default_colors_set" => Some(parse_default_colors(event_parameters)?),
             "hl_attr_define" => Some(parse_hl_attr_define(event_parameters)?),
+            "hl_group_set" => Some(parse_hl_group_set(event_parameters)?),
             "grid_line" => Some(parse_grid_line(event_parameters)?),
             "grid_clear" => Some(parse_grid_clear(event_parameters)?),
             "grid_destroy" => Some(parse_grid_destroy(event_parameters)?),
@@ -867,6 +1011,10 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
             "msg_showcmd" => Some(parse_msg_showcmd(event_parameters)?),
             "msg_ruler" => Some(parse_msg_ruler(event_parameters)?),
             "msg_history_show" => Some(parse_msg_history_show(event_parameters)?),
+            "popupmenu_show" => Some(parse_popupmenu_show(event_parameters)?),
+            "popupmenu_select" => Some(parse_popupmenu_select(event_parameters)?),
+            "popupmenu_hide" => Some(RedrawEvent::PopupMenuHide),
+            "tabline_update" => Some(parse_tabline_update(event_parameters)?),
             _ => None,
         };
 
@@ -877,3 +1025,49 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
 
     Ok(parsed_events)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style_from(attributes: Vec<(&str, Value)>) -> Style {
+        parse_style(Value::Map(
+            attributes
+                .into_iter()
+                .map(|(name, value)| (Value::from(name), value))
+                .collect(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_underline_styles() {
+        let underline = |name| style_from(vec![(name, Value::from(true))]).underline;
+
+        assert_eq!(underline("underline"), Some(UnderlineStyle::Underline));
+        assert_eq!(underline("underdouble"), Some(UnderlineStyle::UnderDouble));
+        assert_eq!(
+            underline("underlineline"),
+            Some(UnderlineStyle::UnderDouble)
+        );
+        assert_eq!(underline("undercurl"), Some(UnderlineStyle::UnderCurl));
+        assert_eq!(underline("underdotted"), Some(UnderlineStyle::UnderDotted));
+        assert_eq!(underline("underdashed"), Some(UnderlineStyle::UnderDashed));
+        assert_eq!(
+            style_from(vec![("undercurl", Value::from(false))]).underline,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_special_color() {
+        // The color of underlines, set with guisp
+        let style = style_from(vec![
+            ("special", Value::from(0x00ff00)),
+            ("undercurl", Value::from(true)),
+        ]);
+
+        assert_eq!(style.colors.special, Some(unpack_color(0x00ff00)));
+        assert_eq!(style.underline, Some(UnderlineStyle::UnderCurl));
+    }
+}
@@ -1,11 +1,18 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use log::trace;
+use log::{error, trace, warn};
 use nvim_rs::{Handler, Neovim};
+use parking_lot::Mutex;
 use rmpv::Value;
 
-use crate::bridge::clipboard::{get_remote_clipboard, set_remote_clipboard};
 #[cfg(windows)]
 use crate::bridge::ui_commands::{ParallelCommand, UiCommand};
+use crate::bridge::{
+    api::{ApiNotification, ApiRequest, ApiResponder, ApiResult},
+    clipboard::{get_remote_clipboard, set_remote_clipboard},
+    recording::RedrawRecorder,
+};
 use crate::{
     bridge::{events::parse_redraw_event, TxWrapper},
     editor::EditorCommand,
@@ -13,31 +20,26 @@ use crate::{
     event_aggregator::EVENT_AGGREGATOR,
     running_tracker::*,
     settings::SETTINGS,
+    window::{WindowCommand, WindowSettings},
 };
 
 #[derive(Clone)]
-pub struct NeovimHandler {}
+pub struct NeovimHandler {
+    recorder: Option<Arc<Mutex<RedrawRecorder>>>,
+}
 
 impl NeovimHandler {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(recorder: Option<Arc<Mutex<RedrawRecorder>>>) -> Self {
+        Self { recorder }
     }
-}
-
-#[async_trait]
-impl Handler for NeovimHandler {
-    type Writer = TxWrapper;
 
-    async fn handle_request(
+    async fn handle_api_request(
         &self,
-        event_name: String,
-        _arguments: Vec<Value>,
+        request: ApiRequest,
         neovim: Neovim<TxWrapper>,
-    ) -> Result<Value, Value> {
-        trace!("Neovim request: {:?}", &event_name);
-
-        match event_name.as_ref() {
-            "neovide.get_clipboard" => {
+    ) -> ApiResult {
+        match request {
+            ApiRequest::GetClipboard { register } => {
                 let endline_type = neovim
                     .command_output("set ff")
                     .await
@@ -48,23 +50,78 @@ impl Handler for NeovimHandler {
                         s.next().map(String::from)
                     });
 
-                get_remote_clipboard(endline_type.as_deref())
-                    .map_err(|_| Value::from("cannot get remote clipboard content"))
+                get_remote_clipboard(endline_type.as_deref(), &register)
+                    .map_err(|_| "cannot get remote clipboard content".to_owned())
+            }
+            // Window settings are changed through their variables, so neovim stays the source of
+            // truth for them
+            ApiRequest::ToggleFullscreen => {
+                let fullscreen = SETTINGS.get::<WindowSettings>().fullscreen;
+                neovim
+                    .set_var("neovide_fullscreen", Value::from(!fullscreen))
+                    .await
+                    .map(|_| Value::Nil)
+                    .map_err(|error| error.to_string())
+            }
+            ApiRequest::SetScale(scale) => neovim
+                .set_var("neovide_scale_factor", Value::from(scale))
+                .await
+                .map(|_| Value::Nil)
+                .map_err(|error| error.to_string()),
+            ApiRequest::Window(request) => {
+                let (responder, response) = ApiResponder::new();
+                EVENT_AGGREGATOR.send(WindowCommand::ApiRequest(request, responder));
+                response
+                    .await
+                    .unwrap_or_else(|_| Err("The window closed before answering".to_owned()))
             }
-            _ => Ok(Value::from("rpcrequest not handled")),
         }
     }
+}
+
+#[async_trait]
+impl Handler for NeovimHandler {
+    type Writer = TxWrapper;
+
+    async fn handle_request(
+        &self,
+        event_name: String,
+        arguments: Vec<Value>,
+        neovim: Neovim<TxWrapper>,
+    ) -> Result<Value, Value> {
+        trace!("Neovim request: {:?}", &event_name);
+
+        let request = ApiRequest::parse(&event_name, &arguments).map_err(Value::from)?;
+        self.handle_api_request(request, neovim)
+            .await
+            .map_err(Value::from)
+    }
 
     async fn handle_notify(
         &self,
         event_name: String,
         arguments: Vec<Value>,
-        _neovim: Neovim<TxWrapper>,
+        neovim: Neovim<TxWrapper>,
     ) {
         trace!("Neovim notification: {:?}", &event_name);
 
-        match event_name.as_ref() {
-            "redraw" => {
+        let notification = match ApiNotification::parse(&event_name, arguments) {
+            Some(Ok(notification)) => notification,
+            Some(Err(error)) => {
+                warn!("Invalid {} notification: {}", event_name, error);
+                return;
+            }
+            None => return,
+        };
+
+        match notification {
+            ApiNotification::Redraw(arguments) => {
+                if let Some(recorder) = &self.recorder {
+                    if let Err(error) = recorder.lock().record(&arguments) {
+                        error!("Could not record redraw event: {}", error);
+                    }
+                }
+
                 for events in arguments {
                     let parsed_events = parse_redraw_event(events)
                         .unwrap_or_explained_panic("Could not parse event from neovim");
@@ -74,27 +131,26 @@ impl Handler for NeovimHandler {
                     }
                 }
             }
-            "setting_changed" => {
-                SETTINGS.handle_changed_notification(arguments);
+            ApiNotification::SettingChanged(arguments) => {
+                if let Err(message) = SETTINGS.handle_changed_notification(arguments) {
+                    error!("{}", message);
+                    neovim.err_writeln(&message).await.ok();
+                }
             }
-            "neovide.quit" => {
-                let error_code = arguments[0]
-                    .as_i64()
-                    .expect("Could not parse error code from neovim");
+            ApiNotification::Quit(error_code) => {
                 RUNNING_TRACKER.quit_with_code(error_code as i32, "Quit from neovim");
             }
             #[cfg(windows)]
-            "neovide.register_right_click" => {
+            ApiNotification::RegisterRightClick => {
                 EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::RegisterRightClick));
             }
             #[cfg(windows)]
-            "neovide.unregister_right_click" => {
+            ApiNotification::UnregisterRightClick => {
                 EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::UnregisterRightClick));
             }
-            "neovide.set_clipboard" => {
+            ApiNotification::SetClipboard(arguments) => {
                 set_remote_clipboard(arguments).ok();
             }
-            _ => {}
         }
     }
 }
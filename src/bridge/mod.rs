@@ -1,43 +1,102 @@
+mod api;
+mod capabilities;
 mod clipboard;
 mod command;
 pub mod create;
 mod events;
 mod handler;
+mod recording;
 mod setup;
 mod tx_wrapper;
 mod ui_commands;
 
-use std::{process::exit, sync::Arc, thread};
+use std::{fmt, io, process::exit, sync::Arc, thread, time::Duration};
 
-use log::{error, info};
-use nvim_rs::UiAttachOptions;
+use log::{error, info, trace, warn};
+use nvim_rs::{error::LoopError, Neovim, UiAttachOptions};
+use parking_lot::Mutex;
+use rmpv::Value;
+use tokio::{task::JoinHandle, time::sleep};
 
 use crate::{
-    cmd_line::CmdLineSettings, error_handling::ResultPanicExplanation, running_tracker::*,
+    cmd_line::CmdLineSettings,
+    editor::EditorCommand,
+    error_handling::{show_error, ResultPanicExplanation},
+    event_aggregator::EVENT_AGGREGATOR,
+    renderer::RendererSettings,
+    running_tracker::*,
     settings::*,
+    single_instance,
 };
 
+pub use api::{
+    metrics_value, size_value, ApiResponder, GridRegion, GuiEvent, GuiMetrics, WindowRequest,
+};
+use capabilities::NeovimCapabilities;
 pub use command::create_nvim_command;
 pub use events::*;
 use handler::NeovimHandler;
+use recording::RedrawRecorder;
 use setup::setup_neovide_specific_state;
 pub use tx_wrapper::{TxWrapper, WrapTx};
-pub use ui_commands::{start_ui_command_handler, ParallelCommand, SerialCommand, UiCommand};
+use ui_commands::{
+    discard_queued_ui_commands, register_ui_command_receiver, start_ui_command_handler,
+    UiCommandHandler, UiCommandReceiver,
+};
+pub use ui_commands::{ParallelCommand, SerialCommand, UiCommand};
+
+// Delays between attempts to reconnect to a remote neovim instance. The last delay is repeated
+// until the connection is established again or the window is closed.
+const RECONNECT_DELAYS_MS: &[u64] = &[500, 1000, 2000, 4000, 8000];
+
+// Ui extensions Neovide can't draw without
+const REQUIRED_UI_OPTIONS: &[&str] = &["rgb", "ext_linegrid"];
 
 enum ConnectionMode {
     Child,
     RemoteTcp(String),
+    RemoteServer(String),
+}
+
+impl ConnectionMode {
+    fn is_remote(&self) -> bool {
+        matches!(
+            self,
+            ConnectionMode::RemoteTcp(_) | ConnectionMode::RemoteServer(_)
+        )
+    }
+
+    async fn connect(
+        &self,
+        handler: NeovimHandler,
+    ) -> io::Result<(Neovim<TxWrapper>, JoinHandle<Result<(), Box<LoopError>>>)> {
+        match self {
+            ConnectionMode::Child => {
+                create::new_child_cmd(&mut create_nvim_command(), handler).await
+            }
+            ConnectionMode::RemoteTcp(address) => create::new_tcp(address, handler).await,
+            ConnectionMode::RemoteServer(path) => create::new_path(path, handler).await,
+        }
+    }
 }
 
 fn connection_mode() -> ConnectionMode {
-    if let Some(arg) = SETTINGS.get::<CmdLineSettings>().remote_tcp {
+    let settings = SETTINGS.get::<CmdLineSettings>();
+    if let Some(arg) = settings.remote_tcp {
         ConnectionMode::RemoteTcp(arg)
+    } else if let Some(arg) = settings.server {
+        ConnectionMode::RemoteServer(arg)
     } else {
         ConnectionMode::Child
     }
 }
 
 pub fn start_bridge() {
+    if let Some(path) = SETTINGS.get::<CmdLineSettings>().replay {
+        recording::start_replay(path);
+        return;
+    }
+
     thread::spawn(|| {
         start_neovim_runtime();
     });
@@ -45,57 +104,269 @@ pub fn start_bridge() {
 
 #[tokio::main]
 async fn start_neovim_runtime() {
-    let handler = NeovimHandler::new();
-    let (nvim, io_handler) = match connection_mode() {
-        ConnectionMode::Child => create::new_child_cmd(&mut create_nvim_command(), handler).await,
-        ConnectionMode::RemoteTcp(address) => create::new_tcp(address, handler).await,
+    let connection_mode = connection_mode();
+    let ui_command_receiver = register_ui_command_receiver();
+
+    let recorder = SETTINGS.get::<CmdLineSettings>().record.map(|path| {
+        let recorder = RedrawRecorder::create(&path)
+            .unwrap_or_explained_panic("Could not create redraw recording");
+        Arc::new(Mutex::new(recorder))
+    });
+    let handler = NeovimHandler::new(recorder);
+
+    if SETTINGS.get::<CmdLineSettings>().single_instance {
+        tokio::spawn(single_instance::listen());
     }
-    .unwrap_or_explained_panic("Could not locate or start neovim process");
 
-    // Check the neovim version to ensure its high enough
-    match nvim.command_output("echo has('nvim-0.4')").await.as_deref() {
-        Ok("1") => {} // This is just a guard
-        _ => {
-            error!("Neovide requires nvim version 0.4 or higher. Download the latest version here https://github.com/neovim/neovim/wiki/Installing-Neovim");
-            exit(0);
+    let (mut nvim, mut io_handler) = connection_mode
+        .connect(handler.clone())
+        .await
+        .unwrap_or_explained_panic("Could not locate or start neovim process");
+
+    let mut is_first_attach = true;
+    loop {
+        match attach_neovim(
+            Arc::new(nvim),
+            &connection_mode,
+            ui_command_receiver.clone(),
+        )
+        .await
+        {
+            Ok(ui_command_handler) => {
+                match io_handler.await {
+                    Err(join_error) => error!("Error joining IO loop: '{}'", join_error),
+                    Ok(Err(error)) => {
+                        if !error.is_channel_closed() {
+                            error!("Error: '{}'", error);
+                        }
+                    }
+                    Ok(Ok(())) => {}
+                };
+                ui_command_handler.stop();
+            }
+            Err(error) if is_first_attach => error.fail(),
+            // A link that drops again while attaching is retried like a failed connect
+            Err(error) => {
+                warn!("Could not attach to neovim again: {}", error);
+                io_handler.abort();
+            }
+        }
+        is_first_attach = false;
+
+        if !connection_mode.is_remote() || !RUNNING_TRACKER.is_running() {
+            break;
+        }
+
+        match reconnect(&connection_mode, &handler).await {
+            Some((new_nvim, new_io_handler)) => {
+                nvim = new_nvim;
+                io_handler = new_io_handler;
+                discard_queued_ui_commands(&ui_command_receiver).await;
+            }
+            None => break,
+        }
+    }
+    RUNNING_TRACKER.quit("neovim processed failed");
+}
+
+// Why attaching the ui to a neovim instance failed
+enum AttachError {
+    // The connection broke while attaching
+    Connection(String),
+    // The neovim instance is missing something Neovide can't work without
+    Unsupported(String),
+}
+
+impl AttachError {
+    fn connection(explanation: &str, error: impl ToString) -> AttachError {
+        AttachError::Connection(format!("{}: {}", explanation, error.to_string()))
+    }
+
+    fn fail(self) -> ! {
+        match self {
+            AttachError::Connection(message) => show_error(&message),
+            AttachError::Unsupported(message) => exit_with_error(&message),
+        }
+    }
+}
+
+impl fmt::Display for AttachError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttachError::Connection(message) | AttachError::Unsupported(message) => {
+                write!(formatter, "{}", message)
+            }
         }
     }
+}
 
+async fn attach_neovim(
+    nvim: Arc<Neovim<TxWrapper>>,
+    connection_mode: &ConnectionMode,
+    ui_command_receiver: UiCommandReceiver,
+) -> Result<UiCommandHandler, AttachError> {
+    let capabilities = negotiate_capabilities(&nvim).await?;
     let settings = SETTINGS.get::<CmdLineSettings>();
 
-    let mut is_remote = settings.wsl;
-    if let ConnectionMode::RemoteTcp(_) = connection_mode() {
-        is_remote = true;
+    let is_remote = settings.wsl
+        || settings.ssh.is_some()
+        || settings.exec_wrapper.is_some()
+        || connection_mode.is_remote();
+    setup_neovide_specific_state(&nvim, is_remote, capabilities.channel)
+        .await
+        .map_err(|error| {
+            AttachError::connection("Could not communicate with neovim process", error)
+        })?;
+
+    let mut multi_grid = settings.multi_grid;
+    if multi_grid && !capabilities.has_ui_option("ext_multigrid") {
+        let msg =
+            "Neovide: multigrid was requested, but this neovim does not support ext_multigrid";
+        warn!("{}", msg);
+        nvim.err_writeln(msg).await.ok();
+        multi_grid = false;
     }
-    setup_neovide_specific_state(&nvim, is_remote).await;
 
     let geometry = settings.geometry;
     let mut options = UiAttachOptions::new();
     options.set_linegrid_external(true);
-    options.set_multigrid_external(settings.multi_grid);
+    options.set_multigrid_external(multi_grid);
+    options.set_popupmenu_external(capabilities.has_ui_option("ext_popupmenu"));
     options.set_rgb(true);
 
     // Triggers loading the user's config
     nvim.ui_attach(geometry.width as i64, geometry.height as i64, &options)
         .await
-        .unwrap_or_explained_panic("Could not attach ui to neovim process");
+        .map_err(|error| AttachError::connection("Could not attach ui to neovim process", error))?;
 
     info!("Neovim process attached");
 
-    let nvim = Arc::new(nvim);
-
-    start_ui_command_handler(nvim.clone());
+    let ui_command_handler = start_ui_command_handler(nvim.clone(), ui_command_receiver);
     SETTINGS.read_initial_values(&nvim).await;
-    SETTINGS.setup_changed_listeners(&nvim).await;
+    if let Err(error) = SETTINGS
+        .setup_changed_listeners(&nvim, capabilities.channel)
+        .await
+    {
+        ui_command_handler.stop();
+        return Err(AttachError::connection(
+            "Could not setup setting notifiers",
+            error,
+        ));
+    }
+
+    // Opt-in ui extensions are configured from the user's config, so they can only be enabled
+    // once it has been loaded and the settings have been read
+    let renderer_settings = SETTINGS.get::<RendererSettings>();
+    let extensions = [
+        ("ext_cmdline", renderer_settings.ext_cmdline),
+        ("ext_messages", renderer_settings.ext_messages),
+        ("ext_tabline", renderer_settings.ext_tabline),
+    ];
+    for (extension, enabled) in extensions {
+        if enabled && capabilities.has_ui_option(extension) {
+            nvim.ui_set_option(extension, Value::Boolean(true))
+                .await
+                .unwrap_or_else(|error| error!("Could not enable {}: {}", extension, error));
+        }
+    }
+
+    Ok(ui_command_handler)
+}
+
+// The window disappears as soon as the process exits, so the reason is printed to stderr as well
+// as logged
+fn exit_with_error(message: &str) -> ! {
+    error!("{}", message);
+    eprintln!("{}", message);
+    exit(1);
+}
+
+// Reads the version and supported ui extensions of the attached neovim, failing with an
+// explanation if something Neovide can't work without is missing
+async fn negotiate_capabilities(
+    nvim: &Neovim<TxWrapper>,
+) -> Result<NeovimCapabilities, AttachError> {
+    let api_info = nvim.get_api_info().await.map_err(|error| {
+        AttachError::connection("Could not communicate with neovim process", error)
+    })?;
+    let capabilities = NeovimCapabilities::parse(api_info)
+        .map_err(|error| AttachError::connection("Could not parse neovim api info", error))?;
+
+    info!(
+        "Neovim version {}.{}.{} (api level {})",
+        capabilities.version.major,
+        capabilities.version.minor,
+        capabilities.version.patch,
+        capabilities.api_level
+    );
 
-    match io_handler.await {
-        Err(join_error) => error!("Error joining IO loop: '{}'", join_error),
-        Ok(Err(error)) => {
-            if !error.is_channel_closed() {
-                error!("Error: '{}'", error);
+    // Check the neovim version to ensure its high enough
+    if !capabilities.has_version(0, 4) {
+        return Err(AttachError::Unsupported("Neovide requires nvim version 0.4 or higher. Download the latest version here https://github.com/neovim/neovim/wiki/Installing-Neovim".to_string()));
+    }
+
+    for required_option in REQUIRED_UI_OPTIONS {
+        if !capabilities.has_ui_option(required_option) {
+            return Err(AttachError::Unsupported(format!(
+                "Neovide requires the {} ui extension, which this neovim does not support",
+                required_option
+            )));
+        }
+    }
+
+    Ok(capabilities)
+}
+
+// The delay before the given reconnect attempt, counted from 0. Later attempts keep using the
+// longest delay.
+fn reconnect_delay(attempt: usize) -> Duration {
+    Duration::from_millis(RECONNECT_DELAYS_MS[attempt.min(RECONNECT_DELAYS_MS.len() - 1)])
+}
+
+// Keeps the window open while retrying the connection to a remote neovim instance with an
+// increasing delay. Returns None if neovide was closed while disconnected.
+async fn reconnect(
+    connection_mode: &ConnectionMode,
+    handler: &NeovimHandler,
+) -> Option<(Neovim<TxWrapper>, JoinHandle<Result<(), Box<LoopError>>>)> {
+    let mut attempt = 0;
+    while RUNNING_TRACKER.is_running() {
+        let delay = reconnect_delay(attempt);
+        attempt += 1;
+
+        warn!(
+            "Lost connection to neovim, reconnecting in {}ms (attempt {})",
+            delay.as_millis(),
+            attempt
+        );
+        EVENT_AGGREGATOR.send(EditorCommand::ConnectionLost(format!(
+            "Disconnected from neovim, retrying (attempt {})...",
+            attempt
+        )));
+        sleep(delay).await;
+
+        match connection_mode.connect(handler.clone()).await {
+            Ok(connection) => {
+                info!("Reconnected to neovim after {} attempts", attempt);
+                EVENT_AGGREGATOR.send(EditorCommand::ConnectionRestored);
+                return Some(connection);
             }
+            Err(error) => trace!("Reconnect attempt {} failed: {}", attempt, error),
         }
-        Ok(Ok(())) => {}
-    };
-    RUNNING_TRACKER.quit("neovim processed failed");
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(reconnect_delay(0), Duration::from_millis(500));
+        assert_eq!(reconnect_delay(1), Duration::from_millis(1000));
+        assert_eq!(reconnect_delay(4), Duration::from_millis(8000));
+        assert_eq!(reconnect_delay(100), Duration::from_millis(8000));
+    }
 }
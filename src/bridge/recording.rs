@@ -0,0 +1,271 @@
+//! Recording and replaying of the redraw event stream. A recording is a sequence of msgpack
+//! arrays, each holding the microseconds since the recording started and the raw arguments of
+//! one redraw notification.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, info};
+use rmpv::{
+    decode::{read_value, Error as DecodeError},
+    encode::write_value,
+    Value,
+};
+
+use crate::{
+    bridge::{parse_redraw_event, RedrawEvent},
+    editor::EditorCommand,
+    event_aggregator::EVENT_AGGREGATOR,
+};
+
+pub struct RedrawRecorder {
+    file: File,
+    start: Instant,
+}
+
+impl RedrawRecorder {
+    pub fn create(path: &str) -> io::Result<RedrawRecorder> {
+        info!("Recording redraw events to {}", path);
+        Ok(RedrawRecorder {
+            file: File::create(path)?,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, arguments: &[Value]) -> io::Result<()> {
+        // Entries are written unbuffered, as neovide exits without unwinding
+        write_entry(&mut self.file, self.start.elapsed(), arguments)
+    }
+}
+
+fn write_entry<W: Write>(
+    writer: &mut W,
+    timestamp: Duration,
+    arguments: &[Value],
+) -> io::Result<()> {
+    let entry = Value::Array(vec![
+        Value::from(timestamp.as_micros() as u64),
+        Value::Array(arguments.to_vec()),
+    ]);
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, &entry)?;
+    writer.write_all(&buffer)
+}
+
+fn read_entry<R: Read>(reader: &mut R) -> io::Result<Option<(Duration, Vec<Value>)>> {
+    let entry = match read_value(reader) {
+        Ok(entry) => entry,
+        Err(DecodeError::InvalidMarkerRead(error)) if error.kind() == ErrorKind::UnexpectedEof => {
+            return Ok(None)
+        }
+        Err(error) => return Err(io::Error::new(ErrorKind::InvalidData, error.to_string())),
+    };
+
+    let invalid_entry = || io::Error::new(ErrorKind::InvalidData, "invalid recording entry");
+    match entry {
+        Value::Array(mut values) if values.len() == 2 => {
+            let arguments = values.pop().unwrap();
+            let timestamp = values.pop().unwrap();
+            let timestamp = Duration::from_micros(timestamp.as_u64().ok_or_else(invalid_entry)?);
+            match arguments {
+                Value::Array(arguments) => Ok(Some((timestamp, arguments))),
+                _ => Err(invalid_entry()),
+            }
+        }
+        _ => Err(invalid_entry()),
+    }
+}
+
+fn parse_entry(arguments: Vec<Value>) -> Vec<RedrawEvent> {
+    let mut parsed_events = Vec::new();
+    for events in arguments {
+        match parse_redraw_event(events) {
+            Ok(events) => parsed_events.extend(events),
+            Err(error) => error!("Could not parse recorded event: {}", error),
+        }
+    }
+    parsed_events
+}
+
+/// Feeds a recording to the editor with the original timing, without starting neovim
+pub fn start_replay(path: String) {
+    thread::spawn(move || {
+        if let Err(error) = replay(&path) {
+            error!("Could not replay {}: {}", path, error);
+        }
+
+        // Goes through the editor after the recorded events, so headless rendering can exit once
+        // it has drawn everything instead of when the last event was sent
+        EVENT_AGGREGATOR.send(EditorCommand::EndOfReplay);
+    });
+}
+
+fn replay(path: &str) -> io::Result<()> {
+    info!("Replaying redraw events from {}", path);
+    let mut reader = BufReader::new(File::open(path)?);
+    let start = Instant::now();
+
+    while let Some((timestamp, arguments)) = read_entry(&mut reader)? {
+        if let Some(delay) = timestamp.checked_sub(start.elapsed()) {
+            thread::sleep(delay);
+        }
+
+        for parsed_event in parse_entry(arguments) {
+            EVENT_AGGREGATOR.send(EditorCommand::NeovimRedrawEvent(parsed_event));
+        }
+    }
+
+    info!("Replay of {} finished", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::Cursor,
+        sync::{mpsc::channel, Arc},
+    };
+
+    use skia_safe::Surface;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{
+        dimensions::Dimensions,
+        editor::{DrawCommandBatcher, Editor},
+        renderer::{cursor_renderer::CursorSettings, Renderer, RendererSettings},
+        window::{write_snapshot, WindowSettings, FRAME_DT},
+    };
+
+    fn redraw(name: &str, parameters: Vec<Value>) -> Value {
+        Value::Array(vec![Value::from(name), Value::Array(parameters)])
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let flush = vec![Value::Array(vec![
+            Value::from("flush"),
+            Value::Array(vec![]),
+        ])];
+        let mut recording = Vec::new();
+        write_entry(&mut recording, Duration::from_micros(10), &flush).unwrap();
+        write_entry(&mut recording, Duration::from_micros(25), &flush).unwrap();
+
+        let mut reader = Cursor::new(recording);
+        assert_eq!(
+            read_entry(&mut reader).unwrap(),
+            Some((Duration::from_micros(10), flush.clone()))
+        );
+        assert_eq!(
+            read_entry(&mut reader).unwrap(),
+            Some((Duration::from_micros(25), flush))
+        );
+        assert_eq!(read_entry(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_replay_snapshot() {
+        WindowSettings::register();
+        RendererSettings::register();
+        CursorSettings::register();
+
+        // A 10x4 grid on a black background, with four red cells in the third row
+        let red_highlight = Value::Map(vec![(Value::from("background"), Value::from(0xff0000))]);
+        let red_cells = Value::Array(vec![Value::Array(vec![
+            Value::from(" "),
+            Value::from(1),
+            Value::from(4),
+        ])]);
+        let events = vec![
+            redraw(
+                "default_colors_set",
+                vec![0xffffff, 0x000000, 0xff0000, 0, 0]
+                    .into_iter()
+                    .map(Value::from)
+                    .collect(),
+            ),
+            redraw(
+                "hl_attr_define",
+                vec![
+                    Value::from(1),
+                    red_highlight,
+                    Value::Map(vec![]),
+                    Value::Array(vec![]),
+                ],
+            ),
+            redraw(
+                "grid_resize",
+                vec![Value::from(1), Value::from(10), Value::from(4)],
+            ),
+            redraw(
+                "grid_line",
+                vec![Value::from(1), Value::from(2), Value::from(3), red_cells],
+            ),
+            redraw(
+                "grid_cursor_goto",
+                vec![Value::from(1), Value::from(0), Value::from(0)],
+            ),
+            redraw("flush", vec![]),
+        ];
+        let mut recording = Vec::new();
+        write_entry(&mut recording, Duration::from_micros(0), &events).unwrap();
+
+        let (batch_sender, batch_receiver) = channel();
+        let mut editor = Editor::new();
+        editor.draw_command_batcher = Arc::new(DrawCommandBatcher::with_batch_sender(batch_sender));
+        let mut reader = Cursor::new(recording);
+        while let Some((_, arguments)) = read_entry(&mut reader).unwrap() {
+            for event in parse_entry(arguments) {
+                editor.handle_editor_command(EditorCommand::NeovimRedrawEvent(event));
+            }
+        }
+
+        let mut renderer = Renderer::with_draw_command_receiver(1.0, unbounded_channel().1);
+        let font_dimensions = renderer.grid_renderer.font_dimensions;
+        let size = renderer.grid_renderer.convert_grid_to_physical(Dimensions {
+            width: 10,
+            height: 4,
+        });
+        let mut surface = Surface::new_raster_n32_premul((size.width as i32, size.height as i32))
+            .expect("Could not create raster surface");
+        for draw_commands in batch_receiver.try_iter() {
+            renderer.draw_batch(surface.canvas(), draw_commands, FRAME_DT);
+        }
+
+        let output_dir = env::temp_dir().join(format!("neovide-replay-{}", std::process::id()));
+        fs::create_dir_all(&output_dir).unwrap();
+        let path = output_dir.join("frame.png");
+        write_snapshot(&mut surface, &path).unwrap();
+        let snapshot = image::open(&path).unwrap().to_rgba8();
+        fs::remove_dir_all(&output_dir).ok();
+
+        // Compared at the center of every cell but the one under the cursor, as cell contents
+        // are only spaces and don't depend on the installed fonts
+        for row in 0..4 {
+            for column in 0..10 {
+                if (row, column) == (0, 0) {
+                    continue;
+                }
+                let x = (column * 2 + 1) * font_dimensions.width / 2;
+                let y = (row * 2 + 1) * font_dimensions.height / 2;
+                let expected = if row == 2 && (3..7).contains(&column) {
+                    [255, 0, 0]
+                } else {
+                    [0, 0, 0]
+                };
+                assert_eq!(
+                    snapshot.get_pixel(x as u32, y as u32).0[..3],
+                    expected,
+                    "cell at row {} column {}",
+                    row,
+                    column
+                );
+            }
+        }
+    }
+}
@@ -1,8 +1,8 @@
-use log::{info, warn};
-use nvim_rs::Neovim;
+use log::{error, info};
+use nvim_rs::{error::CallError, Neovim};
 use rmpv::Value;
 
-use crate::{bridge::TxWrapper, error_handling::ResultPanicExplanation};
+use crate::bridge::{api, TxWrapper};
 
 pub async fn setup_neovide_remote_clipboard(nvim: &Neovim<TxWrapper>, neovide_channel: u64) {
     // users can opt-out with
@@ -44,11 +44,13 @@ pub async fn setup_neovide_remote_clipboard(nvim: &Neovim<TxWrapper>, neovide_ch
     nvim.command(&custom_clipboard).await.ok();
 }
 
-pub async fn setup_neovide_specific_state(nvim: &Neovim<TxWrapper>, is_remote: bool) {
+pub async fn setup_neovide_specific_state(
+    nvim: &Neovim<TxWrapper>,
+    is_remote: bool,
+    neovide_channel: u64,
+) -> Result<(), Box<CallError>> {
     // Set variable indicating to user config that neovide is being used
-    nvim.set_var("neovide", Value::Boolean(true))
-        .await
-        .unwrap_or_explained_panic("Could not communicate with neovim process");
+    nvim.set_var("neovide", Value::Boolean(true)).await?;
 
     if let Err(command_error) = nvim.command("runtime! ginit.vim").await {
         nvim.command(&format!(
@@ -79,47 +81,44 @@ pub async fn setup_neovide_specific_state(nvim: &Neovim<TxWrapper>, is_remote: b
     .await
     .ok();
 
-    // Retrieve the channel number for communicating with neovide
-    let neovide_channel: Option<u64> = nvim
-        .get_api_info()
-        .await
-        .ok()
-        .and_then(|info| info[0].as_u64());
-
-    if let Some(neovide_channel) = neovide_channel {
-        // Record the channel to the log
-        info!(
-            "Neovide registered to nvim with channel id {}",
-            neovide_channel
-        );
+    // Record the channel to the log
+    info!(
+        "Neovide registered to nvim with channel id {}",
+        neovide_channel
+    );
 
-        // Create a command for registering right click context hooking
-        #[cfg(windows)]
-        nvim.command(&build_neovide_command(
-            neovide_channel,
-            0,
-            "NeovideRegisterRightClick",
-            "register_right_click",
-        ))
+    // Make the rpc api available to lua config as require('neovide')
+    if let Err(error) = nvim
+        .exec_lua(api::LUA_MODULE, vec![Value::from(neovide_channel)])
         .await
-        .ok();
+    {
+        error!("Could not install the neovide lua module: {}", error);
+    }
 
-        // Create a command for unregistering the right click context hooking
-        #[cfg(windows)]
-        nvim.command(&build_neovide_command(
-            neovide_channel,
-            0,
-            "NeovideUnregisterRightClick",
-            "unregister_right_click",
-        ))
-        .await
-        .ok();
+    // Create a command for registering right click context hooking
+    #[cfg(windows)]
+    nvim.command(&build_neovide_command(
+        neovide_channel,
+        0,
+        "NeovideRegisterRightClick",
+        "register_right_click",
+    ))
+    .await
+    .ok();
 
-        if is_remote {
-            setup_neovide_remote_clipboard(nvim, neovide_channel).await;
-        }
-    } else {
-        warn!("Neovide could not find the correct channel id. Some functionality may be disabled.");
+    // Create a command for unregistering the right click context hooking
+    #[cfg(windows)]
+    nvim.command(&build_neovide_command(
+        neovide_channel,
+        0,
+        "NeovideUnregisterRightClick",
+        "unregister_right_click",
+    ))
+    .await
+    .ok();
+
+    if is_remote {
+        setup_neovide_remote_clipboard(nvim, neovide_channel).await;
     }
 
     // Set some basic rendering options
@@ -131,9 +130,15 @@ pub async fn setup_neovide_specific_state(nvim: &Neovim<TxWrapper>, is_remote: b
         .ok();
 
     // Create auto command for retrieving exit code from neovim on quit
-    nvim.command("autocmd VimLeave * call rpcnotify(1, 'neovide.quit', v:exiting)")
-        .await
-        .ok();
+    // silent! since other guis attached to the same neovim may have already closed their channels
+    nvim.command(&format!(
+        "autocmd VimLeave * silent! call rpcnotify({}, 'neovide.quit', v:exiting)",
+        neovide_channel
+    ))
+    .await
+    .ok();
+
+    Ok(())
 }
 
 #[cfg(windows)]
@@ -5,6 +5,10 @@ use std::{
 };
 
 use pin_project::pin_project;
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::NamedPipeClient;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::{
     io::{AsyncWrite, WriteHalf},
     net::TcpStream,
@@ -15,6 +19,10 @@ use tokio::{
 pub enum TxWrapper {
     Child(#[pin] ChildStdin),
     Tcp(#[pin] WriteHalf<TcpStream>),
+    #[cfg(unix)]
+    UnixSocket(#[pin] WriteHalf<UnixStream>),
+    #[cfg(windows)]
+    NamedPipe(#[pin] WriteHalf<NamedPipeClient>),
 }
 
 impl futures::io::AsyncWrite for TxWrapper {
@@ -26,6 +34,10 @@ impl futures::io::AsyncWrite for TxWrapper {
         match self.project() {
             TxProj::Child(inner) => inner.poll_write(cx, buf),
             TxProj::Tcp(inner) => inner.poll_write(cx, buf),
+            #[cfg(unix)]
+            TxProj::UnixSocket(inner) => inner.poll_write(cx, buf),
+            #[cfg(windows)]
+            TxProj::NamedPipe(inner) => inner.poll_write(cx, buf),
         }
     }
 
@@ -33,6 +45,10 @@ impl futures::io::AsyncWrite for TxWrapper {
         match self.project() {
             TxProj::Child(inner) => inner.poll_flush(cx),
             TxProj::Tcp(inner) => inner.poll_flush(cx),
+            #[cfg(unix)]
+            TxProj::UnixSocket(inner) => inner.poll_flush(cx),
+            #[cfg(windows)]
+            TxProj::NamedPipe(inner) => inner.poll_flush(cx),
         }
     }
 
@@ -40,6 +56,10 @@ impl futures::io::AsyncWrite for TxWrapper {
         match self.project() {
             TxProj::Child(inner) => inner.poll_shutdown(cx),
             TxProj::Tcp(inner) => inner.poll_shutdown(cx),
+            #[cfg(unix)]
+            TxProj::UnixSocket(inner) => inner.poll_shutdown(cx),
+            #[cfg(windows)]
+            TxProj::NamedPipe(inner) => inner.poll_shutdown(cx),
         }
     }
 }
@@ -59,3 +79,17 @@ impl WrapTx for WriteHalf<TcpStream> {
         TxWrapper::Tcp(self)
     }
 }
+
+#[cfg(unix)]
+impl WrapTx for WriteHalf<UnixStream> {
+    fn wrap_tx(self) -> TxWrapper {
+        TxWrapper::UnixSocket(self)
+    }
+}
+
+#[cfg(windows)]
+impl WrapTx for WriteHalf<NamedPipeClient> {
+    fn wrap_tx(self) -> TxWrapper {
+        TxWrapper::NamedPipe(self)
+    }
+}
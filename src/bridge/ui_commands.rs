@@ -5,14 +5,23 @@ use log::error;
 use log::trace;
 
 use nvim_rs::{call_args, rpc::model::IntoVal, Neovim};
-use tokio::sync::mpsc::unbounded_channel;
+use rmpv::Value;
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver},
+        Mutex,
+    },
+    task::JoinHandle,
+};
 
 #[cfg(windows)]
 use crate::windows_utils::{
     register_rightclick_directory, register_rightclick_file, unregister_rightclick,
 };
 use crate::{
-    bridge::TxWrapper, event_aggregator::EVENT_AGGREGATOR, running_tracker::RUNNING_TRACKER,
+    bridge::{GuiEvent, GuiMetrics, TxWrapper},
+    event_aggregator::EVENT_AGGREGATOR,
+    running_tracker::RUNNING_TRACKER,
 };
 
 // Serial commands are any commands which must complete before the next value is sent. This
@@ -114,15 +123,66 @@ pub enum ParallelCommand {
         height: u64,
     },
     FileDrop(String),
+    OpenFiles {
+        files: Vec<String>,
+        no_tabs: bool,
+    },
     FocusLost,
     FocusGained,
     DisplayAvailableFonts(Vec<String>),
+    PublishMetrics(GuiMetrics),
+    GuiEvent(GuiEvent),
+    SwitchTab(u64),
+    CloseTab(u64),
+    MoveTab {
+        tab: u64,
+        // Number of the tab it is placed after, like the argument of `:tabmove`
+        after: u64,
+    },
     #[cfg(windows)]
     RegisterRightClick,
     #[cfg(windows)]
     UnregisterRightClick,
 }
 
+// Paths come from dropped files and other processes, so they are passed to neovim as an argument
+// and escaped there rather than being formatted into the ex command
+async fn open_file(nvim: &Neovim<TxWrapper>, open_command: &str, path: &str) {
+    nvim.exec_lua(
+        "local command, path = ...\nvim.cmd(command .. ' ' .. vim.fn.fnameescape(path))",
+        vec![Value::from(open_command), Value::from(path)],
+    )
+    .await
+    .ok();
+}
+
+// Only fired when defined, as doautocmd complains about patterns without autocommands
+async fn do_user_autocmd(nvim: &Neovim<TxWrapper>, pattern: &str) {
+    nvim.command(&format!(
+        "if exists('#User#{0}') | doautocmd <nomodeline> User {0} | endif",
+        pattern
+    ))
+    .await
+    .ok();
+}
+
+// Parallel commands run concurrently, so g:neovide_event is set in the same request that fires
+// the autocommand. Otherwise events sent together could see each other's details
+async fn fire_gui_event(nvim: &Neovim<TxWrapper>, event: GuiEvent) {
+    nvim.exec_lua(
+        concat!(
+            "local pattern, details = ...\n",
+            "vim.api.nvim_set_var('neovide_event', details)\n",
+            "if vim.fn.exists('#User#' .. pattern) == 1 then\n",
+            "  vim.api.nvim_command('doautocmd <nomodeline> User ' .. pattern)\n",
+            "end",
+        ),
+        vec![Value::from(event.autocmd_pattern()), event.details()],
+    )
+    .await
+    .ok();
+}
+
 impl ParallelCommand {
     async fn execute(self, nvim: &Neovim<TxWrapper>) {
         match self {
@@ -146,7 +206,43 @@ impl ParallelCommand {
                 .await
                 .expect("Focus Gained Failed"),
             ParallelCommand::FileDrop(path) => {
-                nvim.command(format!("e {}", path).as_str()).await.ok();
+                open_file(nvim, "edit", &path).await;
+                fire_gui_event(nvim, GuiEvent::FileDropped(path)).await;
+            }
+            ParallelCommand::OpenFiles { files, no_tabs } => {
+                let open_command = if no_tabs { "edit" } else { "tabnew" };
+                for path in files {
+                    open_file(nvim, open_command, &path).await;
+                }
+            }
+            ParallelCommand::PublishMetrics(metrics) => {
+                nvim.set_var("neovide_metrics", metrics.to_value())
+                    .await
+                    .ok();
+                do_user_autocmd(nvim, "NeovideMetricsChanged").await;
+            }
+            ParallelCommand::GuiEvent(event) => fire_gui_event(nvim, event).await,
+            ParallelCommand::SwitchTab(tab) => {
+                nvim.command(&format!("call nvim_set_current_tabpage({})", tab))
+                    .await
+                    .ok();
+            }
+            ParallelCommand::CloseTab(tab) => {
+                // Closing a tab with unsaved changes fails, which neovim reports on its own
+                nvim.command(&format!(
+                    "execute nvim_tabpage_get_number({}) . 'tabclose'",
+                    tab
+                ))
+                .await
+                .ok();
+            }
+            ParallelCommand::MoveTab { tab, after } => {
+                nvim.command(&format!(
+                    "call nvim_set_current_tabpage({}) | tabmove {}",
+                    tab, after
+                ))
+                .await
+                .ok();
             }
             ParallelCommand::DisplayAvailableFonts(fonts) => {
                 let mut content: Vec<String> = vec![
@@ -241,11 +337,59 @@ impl From<ParallelCommand> for UiCommand {
     }
 }
 
-pub fn start_ui_command_handler(nvim: Arc<Neovim<TxWrapper>>) {
+pub type UiCommandReceiver = Arc<Mutex<UnboundedReceiver<UiCommand>>>;
+
+pub fn register_ui_command_receiver() -> UiCommandReceiver {
+    Arc::new(Mutex::new(EVENT_AGGREGATOR.register_event::<UiCommand>()))
+}
+
+// Commands queued while disconnected, such as keys typed into the frozen window, were meant for the
+// lost session. Only the size of the window still applies to the new one.
+fn coalesce_queued_commands(commands: Vec<UiCommand>) -> Option<UiCommand> {
+    commands
+        .into_iter()
+        .filter(|command| matches!(command, UiCommand::Parallel(ParallelCommand::Resize { .. })))
+        .last()
+}
+
+pub async fn discard_queued_ui_commands(ui_command_receiver: &UiCommandReceiver) {
+    let mut ui_command_receiver = ui_command_receiver.lock().await;
+    let mut commands = Vec::new();
+    while let Ok(command) = ui_command_receiver.try_recv() {
+        commands.push(command);
+    }
+
+    trace!(
+        "Discarding {} ui commands queued while disconnected",
+        commands.len()
+    );
+    if let Some(resize) = coalesce_queued_commands(commands) {
+        EVENT_AGGREGATOR.send(resize);
+    }
+}
+
+// The tasks forwarding ui commands to a single neovim connection. Stopping them releases the
+// shared receiver so that the handler can be restarted once a new connection is established.
+pub struct UiCommandHandler {
+    dispatch_task: JoinHandle<()>,
+    serial_task: JoinHandle<()>,
+}
+
+impl UiCommandHandler {
+    pub fn stop(self) {
+        self.dispatch_task.abort();
+        self.serial_task.abort();
+    }
+}
+
+pub fn start_ui_command_handler(
+    nvim: Arc<Neovim<TxWrapper>>,
+    ui_command_receiver: UiCommandReceiver,
+) -> UiCommandHandler {
     let (serial_tx, mut serial_rx) = unbounded_channel::<SerialCommand>();
     let ui_command_nvim = nvim.clone();
-    tokio::spawn(async move {
-        let mut ui_command_receiver = EVENT_AGGREGATOR.register_event::<UiCommand>();
+    let dispatch_task = tokio::spawn(async move {
+        let mut ui_command_receiver = ui_command_receiver.lock().await;
         while RUNNING_TRACKER.is_running() {
             match ui_command_receiver.recv().await {
                 Some(UiCommand::Serial(serial_command)) => serial_tx
@@ -264,7 +408,7 @@ pub fn start_ui_command_handler(nvim: Arc<Neovim<TxWrapper>>) {
         }
     });
 
-    tokio::spawn(async move {
+    let serial_task = tokio::spawn(async move {
         while RUNNING_TRACKER.is_running() {
             match serial_rx.recv().await {
                 Some(serial_command) => {
@@ -276,4 +420,43 @@ pub fn start_ui_command_handler(nvim: Arc<Neovim<TxWrapper>>) {
             }
         }
     });
+
+    UiCommandHandler {
+        dispatch_task,
+        serial_task,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coalesce_queued_commands() {
+        let commands = vec![
+            UiCommand::Parallel(ParallelCommand::Resize {
+                width: 80,
+                height: 24,
+            }),
+            UiCommand::Serial(SerialCommand::Keyboard("i".to_string())),
+            UiCommand::Parallel(ParallelCommand::Resize {
+                width: 100,
+                height: 30,
+            }),
+            UiCommand::Parallel(ParallelCommand::FocusLost),
+        ];
+        assert!(matches!(
+            coalesce_queued_commands(commands),
+            Some(UiCommand::Parallel(ParallelCommand::Resize {
+                width: 100,
+                height: 30
+            }))
+        ));
+        assert!(
+            coalesce_queued_commands(vec![UiCommand::Serial(SerialCommand::Keyboard(
+                "i".to_string()
+            ))])
+            .is_none()
+        );
+    }
 }
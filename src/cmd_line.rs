@@ -6,18 +6,27 @@ use clap::{Arg, Command};
 pub struct CmdLineSettings {
     // Pass through arguments
     pub neovim_args: Vec<String>,
+    pub files_to_open: Vec<String>,
     // Command-line arguments only
     pub geometry: Dimensions,
     pub log_to_file: bool,
     pub no_fork: bool,
     pub remote_tcp: Option<String>,
+    pub server: Option<String>,
+    pub ssh: Option<String>,
+    pub exec_wrapper: Option<String>,
     pub wsl: bool,
+    pub single_instance: bool,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub headless: Option<String>,
     // Command-line flags with environment variable fallback
     pub frame: Frame,
     pub maximized: bool,
     pub multi_grid: bool,
     pub no_idle: bool,
     pub srgb: bool,
+    pub software_render: bool,
     // Command-line arguments with environment variable fallback
     pub neovim_bin: Option<String>,
     pub wayland_app_id: String,
@@ -31,18 +40,27 @@ impl Default for CmdLineSettings {
         Self {
             // Pass through arguments
             neovim_args: vec![],
+            files_to_open: vec![],
             // Command-line arguments only
             geometry: DEFAULT_WINDOW_GEOMETRY,
             log_to_file: false,
             no_fork: false,
             remote_tcp: None,
+            server: None,
+            ssh: None,
+            exec_wrapper: None,
             wsl: false,
+            single_instance: false,
+            record: None,
+            replay: None,
+            headless: None,
             // Command-line flags with environment variable fallback
             frame: Frame::Full,
             maximized: false,
             multi_grid: false,
             no_idle: false,
             srgb: true,
+            software_render: false,
             // Command-line arguments with environment variable fallback
             neovim_bin: None,
             wayland_app_id: String::new(),
@@ -100,11 +118,58 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
                 .takes_value(true)
                 .help("Connect to Remote TCP"),
         )
+        .arg(
+            Arg::new("server")
+                .long("server")
+                .takes_value(true)
+                .conflicts_with("remote_tcp")
+                .help("Connect to a neovim server listening on a unix socket or named pipe"),
+        )
+        .arg(
+            Arg::new("ssh")
+                .long("ssh")
+                .takes_value(true)
+                .conflicts_with_all(&["remote_tcp", "server"])
+                .help("Run neovim on a remote host over ssh, e.g. --ssh user@host"),
+        )
+        .arg(
+            Arg::new("exec_wrapper")
+                .long("exec-wrapper")
+                .takes_value(true)
+                .conflicts_with_all(&["remote_tcp", "server", "ssh", "wsl"])
+                .help("Run neovim through a wrapper command, e.g. --exec-wrapper \"podman exec -i mybox\""),
+        )
         .arg(
             Arg::new("wsl")
                 .long("wsl")
                 .help("Run in WSL")
         )
+        .arg(
+            Arg::new("single_instance")
+                .long("remote")
+                .alias("single-instance")
+                .help("Open files in an already running Neovide instead of starting a new one"),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .takes_value(true)
+                .help("Record the redraw events received from neovim to a file"),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .takes_value(true)
+                .conflicts_with_all(&["record", "remote_tcp", "server", "ssh", "exec_wrapper"])
+                .help("Replay redraw events recorded with --record instead of starting neovim"),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Render without a window, writing a png snapshot to DIR after every flush"),
+        )
         // Command-line flags with environment variable fallback
         .arg(
             Arg::new("frame")
@@ -132,6 +197,11 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
                 .long("nosrgb")
                 .help("Do not use standard color space to initialize the window. Swapping this variable sometimes fixes issues on startup"),
         )
+        .arg(
+            Arg::new("software_render")
+                .long("software-render")
+                .help("Render on the cpu instead of using OpenGL. Slower, but works without a gpu"),
+        )
         // Command-line arguments with environment variable fallback
         .arg(
             Arg::new("neovim_bin")
@@ -170,7 +240,7 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
         neovim_args.push("-p".to_owned());
     }
 
-    neovim_args.extend::<Vec<String>>(files_to_open);
+    neovim_args.extend::<Vec<String>>(files_to_open.clone());
 
     /*
      * Integrate Environment Variables as Defaults to the command-line ones.
@@ -180,12 +250,20 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
     SETTINGS.set::<CmdLineSettings>(&CmdLineSettings {
         // Pass through arguments
         neovim_args,
+        files_to_open,
         // Command-line arguments only
         geometry: parse_window_geometry(matches.value_of("geometry").map(|i| i.to_owned()))?,
         log_to_file: matches.is_present("log_to_file"),
         no_fork: matches.is_present("nofork"),
         remote_tcp: matches.value_of("remote_tcp").map(|i| i.to_owned()),
+        server: matches.value_of("server").map(|i| i.to_owned()),
+        ssh: matches.value_of("ssh").map(|i| i.to_owned()),
+        exec_wrapper: matches.value_of("exec_wrapper").map(|i| i.to_owned()),
         wsl: matches.is_present("wsl"),
+        single_instance: matches.is_present("single_instance"),
+        record: matches.value_of("record").map(|i| i.to_owned()),
+        replay: matches.value_of("replay").map(|i| i.to_owned()),
+        headless: matches.value_of("headless").map(|i| i.to_owned()),
         // Command-line flags with environment variable fallback
         frame: match matches.value_of("frame") {
             Some(val) => Frame::from_string(val.to_string()),
@@ -199,6 +277,8 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
         no_idle: matches.is_present("noidle") || std::env::var("NEOVIDE_NO_IDLE").is_ok(),
         // Srgb is enabled by default, so set it to false if nosrgb or NOEVIDE_NO_SRGB is set
         srgb: !(matches.is_present("nosrgb") || std::env::var("NEOVIDE_NO_SRGB").is_ok()),
+        software_render: matches.is_present("software_render")
+            || std::env::var("NEOVIDE_SOFTWARE_RENDER").is_ok(),
         // Command-line arguments with environment variable fallback
         neovim_bin: matches
             .value_of("neovim_bin")
@@ -264,6 +344,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_single_instance() {
+        let args: Vec<String> = vec!["neovide", "--remote", "./foo.txt"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        handle_command_line_arguments(args).expect("Could not parse arguments");
+        assert!(SETTINGS.get::<CmdLineSettings>().single_instance);
+        assert_eq!(
+            SETTINGS.get::<CmdLineSettings>().files_to_open,
+            vec!["./foo.txt"]
+        );
+    }
+
     #[test]
     fn test_files_to_open_with_passthrough() {
         let args: Vec<String> = vec![
@@ -345,6 +441,36 @@ mod tests {
         assert!(SETTINGS.get::<CmdLineSettings>().log_to_file);
     }
 
+    #[test]
+    fn test_server_arg() {
+        let args: Vec<String> = vec!["neovide", "--server", "/tmp/nvim.sock"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        handle_command_line_arguments(args).expect("Could not parse arguments");
+        assert_eq!(
+            SETTINGS.get::<CmdLineSettings>().server,
+            Some("/tmp/nvim.sock".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_replay_arg() {
+        let args: Vec<String> = vec!["neovide", "--replay", "session.redraw"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        handle_command_line_arguments(args).expect("Could not parse arguments");
+        assert_eq!(
+            SETTINGS.get::<CmdLineSettings>().replay,
+            Some("session.redraw".to_owned())
+        );
+    }
+
     #[test]
     fn test_frameless_flag() {
         let args: Vec<String> = vec!["neovide", "--frame=full"]
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::editor::{Style, StyledText};
+
+#[derive(Clone, Debug)]
+pub struct CommandLineLevel {
+    pub content: StyledText,
+    // Byte offset of the cursor into the content
+    pub position: u64,
+    pub first_character: String,
+    pub prompt: String,
+    pub indent: u64,
+    pub special_character: Option<(String, bool)>,
+}
+
+impl CommandLineLevel {
+    // Width in cells of everything drawn in front of the content
+    pub fn prefix_width(&self) -> usize {
+        self.first_character.width() + self.prompt.width() + self.indent as usize
+    }
+
+    pub fn cursor_column(&self) -> usize {
+        self.column_at(self.position)
+    }
+
+    // Converts a byte offset into the content to the cell column it is drawn at
+    pub fn column_at(&self, position: u64) -> usize {
+        self.prefix_width() + self.characters_before(position).1
+    }
+
+    // The number of characters starting in front of a byte offset into the content, and the
+    // number of cells they take up. An offset inside of a character counts that character.
+    fn characters_before(&self, position: u64) -> (usize, usize) {
+        let mut remaining = position as usize;
+        let (mut characters, mut cells) = (0, 0);
+        for (_, text) in &self.content {
+            for (offset, character) in text.char_indices() {
+                if offset >= remaining {
+                    return (characters, cells);
+                }
+                characters += 1;
+                cells += character.width().unwrap_or(0);
+            }
+            remaining = remaining.saturating_sub(text.len());
+        }
+
+        (characters, cells)
+    }
+
+    // The content with the special character typed after <C-v> or <C-k> shown at the cursor. When
+    // shift is set it is inserted, otherwise it covers the character under the cursor.
+    pub fn displayed_content(&self) -> StyledText {
+        let (character, shift) = match &self.special_character {
+            Some(special_character) => special_character,
+            None => return self.content.clone(),
+        };
+
+        let mut characters: Vec<(Option<Arc<Style>>, char)> = self
+            .content
+            .iter()
+            .flat_map(|(style, text)| text.chars().map(move |c| (style.clone(), c)))
+            .collect();
+        let (cursor, _) = self.characters_before(self.position);
+        if !shift && cursor < characters.len() {
+            characters.remove(cursor);
+        }
+        let insert_at = cursor.min(characters.len());
+        for (offset, c) in character.chars().enumerate() {
+            characters.insert(insert_at + offset, (None, c));
+        }
+
+        let mut content: StyledText = Vec::new();
+        for (style, c) in characters {
+            match content.last_mut() {
+                Some((last_style, text)) if *last_style == style => text.push(c),
+                _ => content.push((style, c.to_string())),
+            }
+        }
+        content
+    }
+}
+
+// Command lines can be nested, for example when using <C-r>= while typing a command, so every
+// level is kept and only the innermost one is drawn
+#[derive(Clone, Debug, Default)]
+pub struct CommandLine {
+    pub levels: Vec<CommandLineLevel>,
+    pub block: Vec<StyledText>,
+}
+
+impl CommandLine {
+    pub fn is_visible(&self) -> bool {
+        !self.levels.is_empty() || !self.block.is_empty()
+    }
+
+    pub fn show(&mut self, level: u64, command_line_level: CommandLineLevel) {
+        self.levels.truncate(level.saturating_sub(1) as usize);
+        self.levels.push(command_line_level);
+    }
+
+    pub fn hide(&mut self) {
+        self.levels.pop();
+    }
+
+    fn level_mut(&mut self, level: u64) -> Option<&mut CommandLineLevel> {
+        self.levels.get_mut(level.saturating_sub(1) as usize)
+    }
+
+    pub fn set_position(&mut self, position: u64, level: u64) {
+        if let Some(command_line_level) = self.level_mut(level) {
+            command_line_level.position = position;
+            command_line_level.special_character = None;
+        }
+    }
+
+    pub fn set_special_character(&mut self, character: String, shift: bool, level: u64) {
+        if let Some(command_line_level) = self.level_mut(level) {
+            command_line_level.special_character = Some((character, shift));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(content: &[&str], position: u64) -> CommandLineLevel {
+        CommandLineLevel {
+            content: content
+                .iter()
+                .map(|text| (None, text.to_string()))
+                .collect(),
+            position,
+            first_character: ":".to_owned(),
+            prompt: String::new(),
+            indent: 0,
+            special_character: None,
+        }
+    }
+
+    #[test]
+    fn test_cursor_column() {
+        // "é" is two bytes long but a single cell wide
+        let command_line_level = level(&["echo ", "\"é\""], 8);
+        assert_eq!(command_line_level.cursor_column(), 1 + 7);
+        assert_eq!(level(&["wq"], 2).cursor_column(), 3);
+        // Wide characters take up two cells, and an offset inside of one counts it
+        assert_eq!(level(&["e ", "日本"], 5).cursor_column(), 1 + 4);
+        assert_eq!(level(&["e ", "日本"], 4).cursor_column(), 1 + 4);
+    }
+
+    #[test]
+    fn test_displayed_content() {
+        let mut command_line_level = level(&["abc"], 1);
+        command_line_level.special_character = Some(("^".to_owned(), true));
+        assert_eq!(
+            command_line_level.displayed_content(),
+            vec![(None, "a^bc".to_owned())]
+        );
+
+        command_line_level.special_character = Some(("^".to_owned(), false));
+        assert_eq!(
+            command_line_level.displayed_content(),
+            vec![(None, "a^c".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_nested_levels() {
+        let mut command_line = CommandLine::default();
+        command_line.show(1, level(&["echo "], 5));
+        command_line.show(2, level(&["1+1"], 3));
+        assert_eq!(command_line.levels.len(), 2);
+
+        command_line.hide();
+        assert_eq!(command_line.levels.len(), 1);
+        assert!(command_line.is_visible());
+
+        command_line.hide();
+        assert!(!command_line.is_visible());
+    }
+}
@@ -24,6 +24,51 @@ impl CursorShape {
     }
 }
 
+// Pointer shapes neovim refers to by their index in its 'mouseshape' table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseShape {
+    Arrow,
+    Blank,
+    Beam,
+    UpDown,
+    UpDownSizing,
+    LeftRight,
+    LeftRightSizing,
+    Busy,
+    No,
+    Crosshair,
+    Hand,
+    Hand2,
+    Pencil,
+    Question,
+    RightUpArrow,
+    UpArrow,
+}
+
+impl MouseShape {
+    pub fn from_id(id: u64) -> Option<MouseShape> {
+        match id {
+            0 => Some(MouseShape::Arrow),
+            1 => Some(MouseShape::Blank),
+            2 => Some(MouseShape::Beam),
+            3 => Some(MouseShape::UpDown),
+            4 => Some(MouseShape::UpDownSizing),
+            5 => Some(MouseShape::LeftRight),
+            6 => Some(MouseShape::LeftRightSizing),
+            7 => Some(MouseShape::Busy),
+            8 => Some(MouseShape::No),
+            9 => Some(MouseShape::Crosshair),
+            10 => Some(MouseShape::Hand),
+            11 => Some(MouseShape::Hand2),
+            12 => Some(MouseShape::Pencil),
+            13 => Some(MouseShape::Question),
+            14 => Some(MouseShape::RightUpArrow),
+            15 => Some(MouseShape::UpArrow),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct CursorMode {
     pub shape: Option<CursorShape>,
@@ -32,6 +77,7 @@ pub struct CursorMode {
     pub blinkwait: Option<u64>,
     pub blinkon: Option<u64>,
     pub blinkoff: Option<u64>,
+    pub mouse_shape: Option<MouseShape>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -96,6 +142,7 @@ impl Cursor {
             blinkwait,
             blinkon,
             blinkoff,
+            ..
         } = cursor_mode;
 
         if let Some(shape) = shape {
@@ -135,6 +182,14 @@ mod tests {
         special: None,
     };
 
+    #[test]
+    fn test_mouse_shape_from_id() {
+        assert_eq!(MouseShape::from_id(0), Some(MouseShape::Arrow));
+        assert_eq!(MouseShape::from_id(2), Some(MouseShape::Beam));
+        assert_eq!(MouseShape::from_id(7), Some(MouseShape::Busy));
+        assert_eq!(MouseShape::from_id(16), None);
+    }
+
     #[test]
     fn test_from_type_name() {
         assert_eq!(
@@ -204,6 +259,7 @@ mod tests {
             blinkwait: Some(1),
             blinkon: Some(1),
             blinkoff: Some(1),
+            mouse_shape: Some(MouseShape::Beam),
         };
         let mut styles = HashMap::new();
         styles.insert(1, Arc::new(Style::new(COLORS)));
@@ -225,6 +281,7 @@ mod tests {
             blinkwait: None,
             blinkon: None,
             blinkoff: None,
+            mouse_shape: None,
         };
         cursor.change_mode(&cursor_mode_with_none, &styles);
         assert_eq!(cursor.shape, CursorShape::Horizontal);
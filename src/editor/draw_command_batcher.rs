@@ -5,6 +5,8 @@ use crate::{editor::DrawCommand, event_aggregator::EVENT_AGGREGATOR};
 pub struct DrawCommandBatcher {
     window_draw_command_sender: Sender<DrawCommand>,
     window_draw_command_receiver: Receiver<DrawCommand>,
+    // Batches go to the renderer through the event aggregator unless they are collected directly
+    batch_sender: Option<Sender<Vec<DrawCommand>>>,
 }
 
 impl DrawCommandBatcher {
@@ -14,6 +16,15 @@ impl DrawCommandBatcher {
         DrawCommandBatcher {
             window_draw_command_sender: sender,
             window_draw_command_receiver: receiver,
+            batch_sender: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_batch_sender(batch_sender: Sender<Vec<DrawCommand>>) -> DrawCommandBatcher {
+        DrawCommandBatcher {
+            batch_sender: Some(batch_sender),
+            ..DrawCommandBatcher::new()
         }
     }
 
@@ -23,6 +34,10 @@ impl DrawCommandBatcher {
 
     pub fn send_batch(&self) {
         let batch: Vec<DrawCommand> = self.window_draw_command_receiver.try_iter().collect();
-        EVENT_AGGREGATOR.send(batch);
+        if let Some(batch_sender) = &self.batch_sender {
+            batch_sender.send(batch).ok();
+        } else {
+            EVENT_AGGREGATOR.send(batch);
+        }
     }
 }
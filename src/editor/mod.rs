@@ -1,3 +1,4 @@
+mod command_line;
 mod cursor;
 mod draw_command_batcher;
 mod grid;
@@ -9,17 +10,23 @@ use std::{collections::HashMap, sync::Arc, thread};
 use log::{error, trace};
 
 use crate::{
-    bridge::{GuiOption, RedrawEvent, WindowAnchor},
+    bridge::{
+        GuiEvent, GuiOption, ParallelCommand, PopupMenuItem, RedrawEvent, TabInfo, UiCommand,
+        WindowAnchor,
+    },
     event_aggregator::EVENT_AGGREGATOR,
     redraw_scheduler::REDRAW_SCHEDULER,
-    renderer::DrawCommand,
+    renderer::{DrawCommand, PopupMenuAnchor, PopupMenuStyles},
     window::WindowCommand,
 };
 
-pub use cursor::{Cursor, CursorMode, CursorShape};
+pub use command_line::{CommandLine, CommandLineLevel};
+pub use cursor::{Cursor, CursorMode, CursorShape, MouseShape};
 pub use draw_command_batcher::DrawCommandBatcher;
 pub use grid::CharacterGrid;
-pub use style::{Colors, Style};
+pub use style::{
+    blend_to_alpha, resolve_styles, styled_text_width, Colors, Style, StyledText, UnderlineStyle,
+};
 pub use window::*;
 
 #[derive(Clone, Debug)]
@@ -52,15 +59,24 @@ impl WindowAnchor {
 pub enum EditorCommand {
     NeovimRedrawEvent(RedrawEvent),
     RedrawScreen,
+    ConnectionLost(String),
+    ConnectionRestored,
+    EndOfReplay,
 }
 
 pub struct Editor {
     pub windows: HashMap<u64, Window>,
     pub cursor: Cursor,
     pub defined_styles: HashMap<u64, Arc<Style>>,
+    // Highlight ids of the builtin ui highlight groups, like Pmenu
+    pub highlight_groups: HashMap<String, u64>,
     pub mode_list: Vec<CursorMode>,
     pub draw_command_batcher: Arc<DrawCommandBatcher>,
     pub current_mode_index: Option<u64>,
+    pub command_line: CommandLine,
+    pub tabs: Vec<TabInfo>,
+    pub current_tab: u64,
+    pub show_tabline: u64,
 }
 
 impl Editor {
@@ -69,9 +85,14 @@ impl Editor {
             windows: HashMap::new(),
             cursor: Cursor::new(),
             defined_styles: HashMap::new(),
+            highlight_groups: HashMap::new(),
             mode_list: Vec::new(),
             draw_command_batcher: Arc::new(DrawCommandBatcher::new()),
             current_mode_index: None,
+            command_line: CommandLine::default(),
+            tabs: Vec::new(),
+            current_tab: 0,
+            show_tabline: 1,
         }
     }
 
@@ -85,7 +106,9 @@ impl Editor {
                     self.mode_list = cursor_modes;
                     if let Some(current_mode_i) = self.current_mode_index {
                         if let Some(current_mode) = self.mode_list.get(current_mode_i as usize) {
-                            self.cursor.change_mode(current_mode, &self.defined_styles)
+                            self.cursor.change_mode(current_mode, &self.defined_styles);
+                            EVENT_AGGREGATOR
+                                .send(WindowCommand::SetMouseShape(current_mode.mouse_shape));
                         }
                     }
                 }
@@ -93,7 +116,9 @@ impl Editor {
                 RedrawEvent::ModeChange { mode, mode_index } => {
                     if let Some(cursor_mode) = self.mode_list.get(mode_index as usize) {
                         self.cursor.change_mode(cursor_mode, &self.defined_styles);
-                        self.current_mode_index = Some(mode_index)
+                        self.current_mode_index = Some(mode_index);
+                        EVENT_AGGREGATOR
+                            .send(WindowCommand::SetMouseShape(cursor_mode.mouse_shape));
                     } else {
                         self.current_mode_index = None
                     }
@@ -110,10 +135,12 @@ impl Editor {
                 RedrawEvent::BusyStart => {
                     trace!("Cursor off");
                     self.cursor.enabled = false;
+                    EVENT_AGGREGATOR.send(WindowCommand::SetBusy(true));
                 }
                 RedrawEvent::BusyStop => {
                     trace!("Cursor on");
                     self.cursor.enabled = true;
+                    EVENT_AGGREGATOR.send(WindowCommand::SetBusy(false));
                 }
                 RedrawEvent::Flush => {
                     trace!("Image flushed");
@@ -132,6 +159,9 @@ impl Editor {
                 RedrawEvent::HighlightAttributesDefine { id, style } => {
                     self.defined_styles.insert(id, Arc::new(style));
                 }
+                RedrawEvent::HighlightGroupSet { name, id } => {
+                    self.highlight_groups.insert(name, id);
+                }
                 RedrawEvent::CursorGoto {
                     grid,
                     column: left,
@@ -216,12 +246,185 @@ impl Editor {
                     bottom_line,
                     ..
                 } => self.send_updated_viewport(grid, top_line, bottom_line),
+                RedrawEvent::CommandLineShow {
+                    content,
+                    position,
+                    first_character,
+                    prompt,
+                    indent,
+                    level,
+                } => {
+                    let content = resolve_styles(content, &self.defined_styles);
+                    self.command_line.show(
+                        level,
+                        CommandLineLevel {
+                            content,
+                            position,
+                            first_character,
+                            prompt,
+                            indent,
+                            special_character: None,
+                        },
+                    );
+                    self.send_command_line();
+                }
+                RedrawEvent::CommandLinePosition { position, level } => {
+                    self.command_line.set_position(position, level);
+                    self.send_command_line();
+                }
+                RedrawEvent::CommandLineSpecialCharacter {
+                    character,
+                    shift,
+                    level,
+                } => {
+                    self.command_line
+                        .set_special_character(character, shift, level);
+                    self.send_command_line();
+                }
+                RedrawEvent::CommandLineHide => {
+                    self.command_line.hide();
+                    self.send_command_line();
+                }
+                RedrawEvent::CommandLineBlockShow { lines } => {
+                    self.command_line.block = lines
+                        .into_iter()
+                        .map(|line| resolve_styles(line, &self.defined_styles))
+                        .collect();
+                    self.send_command_line();
+                }
+                RedrawEvent::CommandLineBlockAppend { line } => {
+                    let line = resolve_styles(line, &self.defined_styles);
+                    self.command_line.block.push(line);
+                    self.send_command_line();
+                }
+                RedrawEvent::CommandLineBlockHide => {
+                    self.command_line.block.clear();
+                    self.send_command_line();
+                }
+                RedrawEvent::MessageShow {
+                    kind,
+                    content,
+                    replace_last,
+                } => {
+                    let content = resolve_styles(content, &self.defined_styles);
+                    self.draw_command_batcher
+                        .queue(DrawCommand::ShowMessage {
+                            kind,
+                            content,
+                            replace_last,
+                        })
+                        .ok();
+                }
+                RedrawEvent::MessageClear => {
+                    self.draw_command_batcher
+                        .queue(DrawCommand::ClearMessages)
+                        .ok();
+                }
+                RedrawEvent::MessageShowMode { content } => {
+                    let content = resolve_styles(content, &self.defined_styles);
+                    self.draw_command_batcher
+                        .queue(DrawCommand::ShowMode(content))
+                        .ok();
+                }
+                RedrawEvent::MessageShowCommand { content } => {
+                    let content = resolve_styles(content, &self.defined_styles);
+                    self.draw_command_batcher
+                        .queue(DrawCommand::ShowCommand(content))
+                        .ok();
+                }
+                RedrawEvent::MessageRuler { content } => {
+                    let content = resolve_styles(content, &self.defined_styles);
+                    self.draw_command_batcher
+                        .queue(DrawCommand::Ruler(content))
+                        .ok();
+                }
+                RedrawEvent::MessageHistoryShow { entries } => {
+                    let entries = entries
+                        .into_iter()
+                        .map(|(kind, content)| {
+                            (kind, resolve_styles(content, &self.defined_styles))
+                        })
+                        .collect();
+                    self.draw_command_batcher
+                        .queue(DrawCommand::ShowMessageHistory(entries))
+                        .ok();
+                }
+                RedrawEvent::TablineUpdate { current_tab, tabs } => {
+                    self.current_tab = current_tab;
+                    self.tabs = tabs;
+                    self.send_tabline();
+                }
+                RedrawEvent::PopupMenuShow {
+                    items,
+                    selected,
+                    row,
+                    column,
+                    grid,
+                } => self.show_popup_menu(items, selected, row, column, grid),
+                RedrawEvent::PopupMenuSelect { selected } => {
+                    self.draw_command_batcher
+                        .queue(DrawCommand::PopupMenuSelect(selected))
+                        .ok();
+                }
+                RedrawEvent::PopupMenuHide => {
+                    self.draw_command_batcher
+                        .queue(DrawCommand::PopupMenuHide)
+                        .ok();
+                }
                 _ => {}
             },
             EditorCommand::RedrawScreen => self.redraw_screen(),
+            EditorCommand::ConnectionLost(status) => {
+                self.draw_command_batcher
+                    .queue(DrawCommand::ConnectionStatus(Some(status)))
+                    .ok();
+                self.draw_command_batcher.send_batch();
+                REDRAW_SCHEDULER.queue_next_frame();
+            }
+            EditorCommand::ConnectionRestored => self.reset_state(),
+            // Sent after the last recorded event, so headless rendering knows every batch before
+            // it has been flushed
+            EditorCommand::EndOfReplay => {
+                self.draw_command_batcher
+                    .queue(DrawCommand::EndOfReplay)
+                    .ok();
+                self.draw_command_batcher.send_batch();
+            }
         };
     }
 
+    // Drop everything received from the previous neovim connection. The grids are rebuilt from
+    // the redraw events sent after the ui is attached again.
+    fn reset_state(&mut self) {
+        let grids: Vec<u64> = self.windows.keys().copied().collect();
+        for grid in grids {
+            self.close_window(grid);
+        }
+        self.defined_styles.clear();
+        self.highlight_groups.clear();
+        self.mode_list.clear();
+        self.current_mode_index = None;
+        self.cursor = Cursor::new();
+        self.command_line = CommandLine::default();
+        self.send_command_line();
+        self.tabs.clear();
+        self.send_tabline();
+        self.draw_command_batcher
+            .queue(DrawCommand::ClearMessages)
+            .ok();
+        self.draw_command_batcher
+            .queue(DrawCommand::PopupMenuHide)
+            .ok();
+        EVENT_AGGREGATOR.send(WindowCommand::SetBusy(false));
+        EVENT_AGGREGATOR.send(WindowCommand::SetMouseShape(None));
+
+        self.draw_command_batcher
+            .queue(DrawCommand::ConnectionStatus(None))
+            .ok();
+        self.draw_command_batcher.send_batch();
+        REDRAW_SCHEDULER.queue_next_frame();
+    }
+
     fn close_window(&mut self, grid: u64) {
         if let Some(window) = self.windows.remove(&grid) {
             window.close();
@@ -345,6 +548,82 @@ impl Editor {
         }
     }
 
+    fn send_command_line(&mut self) {
+        self.draw_command_batcher
+            .queue(DrawCommand::CommandLine(self.command_line.clone()))
+            .ok();
+    }
+
+    // Follows 'showtabline', where 0 never shows the tabline, 1 shows it when there are at least
+    // two tabs and 2 always shows it
+    fn send_tabline(&mut self) {
+        let visible = match self.show_tabline {
+            0 => false,
+            1 => self.tabs.len() > 1,
+            _ => true,
+        };
+        let tabs = if visible {
+            self.tabs.clone()
+        } else {
+            Vec::new()
+        };
+
+        self.draw_command_batcher
+            .queue(DrawCommand::Tabline {
+                current_tab: self.current_tab,
+                tabs,
+            })
+            .ok();
+    }
+
+    // The popup menu is anchored to the cell the completed word starts at, relative to the grid it
+    // is shown for, in the same way floating windows are anchored to their parent grid. Command
+    // line completions have no grid and are anchored to the command line instead.
+    fn show_popup_menu(
+        &mut self,
+        items: Vec<PopupMenuItem>,
+        selected: Option<u64>,
+        row: u64,
+        column: u64,
+        grid: Option<u64>,
+    ) {
+        let anchor = match grid {
+            Some(grid) => {
+                let (grid_left, grid_top) = self.get_window_top_left(grid).unwrap_or((0.0, 0.0));
+                PopupMenuAnchor::Grid {
+                    left: grid_left + column as f64,
+                    top: grid_top + row as f64,
+                }
+            }
+            None => PopupMenuAnchor::CommandLine { position: column },
+        };
+
+        let styles = PopupMenuStyles {
+            normal: self.group_style("Pmenu"),
+            selected: self.group_style("PmenuSel"),
+            kind: self.group_style("PmenuKind"),
+            kind_selected: self.group_style("PmenuKindSel"),
+            extra: self.group_style("PmenuExtra"),
+            thumb: self.group_style("PmenuThumb"),
+        };
+
+        self.draw_command_batcher
+            .queue(DrawCommand::PopupMenuShow {
+                items,
+                selected,
+                anchor,
+                styles,
+            })
+            .ok();
+    }
+
+    fn group_style(&self, name: &str) -> Option<Arc<Style>> {
+        self.highlight_groups
+            .get(name)
+            .and_then(|id| self.defined_styles.get(id))
+            .cloned()
+    }
+
     fn get_window_top_left(&self, grid: u64) -> Option<(f64, f64)> {
         let window = self.windows.get(&grid)?;
         let window_anchor_info = &window.anchor_info;
@@ -416,16 +695,43 @@ impl Editor {
 
     fn set_option(&mut self, gui_option: GuiOption) {
         trace!("Option set {:?}", &gui_option);
-        if let GuiOption::GuiFont(guifont) = gui_option {
-            if guifont == *"*" {
-                EVENT_AGGREGATOR.send(WindowCommand::ListAvailableFonts);
-            }
+        match gui_option {
+            GuiOption::GuiFont(guifont) => {
+                if guifont == *"*" {
+                    EVENT_AGGREGATOR.send(WindowCommand::ListAvailableFonts);
+                }
 
-            self.draw_command_batcher
-                .queue(DrawCommand::FontChanged(guifont))
-                .ok();
+                EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::GuiEvent(
+                    GuiEvent::FontChanged(guifont.clone()),
+                )));
+                self.draw_command_batcher
+                    .queue(DrawCommand::FontChanged(guifont))
+                    .ok();
+
+                self.redraw_screen();
+            }
+            GuiOption::GuiFontWide(guifontwide) => {
+                self.draw_command_batcher
+                    .queue(DrawCommand::FontWideChanged(guifontwide))
+                    .ok();
 
-            self.redraw_screen();
+                self.redraw_screen();
+            }
+            GuiOption::Pumblend(pumblend) => {
+                self.draw_command_batcher
+                    .queue(DrawCommand::PumblendChanged(pumblend))
+                    .ok();
+            }
+            GuiOption::LineSpace(linespace) => {
+                self.draw_command_batcher
+                    .queue(DrawCommand::LineSpaceChanged(linespace))
+                    .ok();
+            }
+            GuiOption::ShowTabLine(show_tabline) => {
+                self.show_tabline = show_tabline;
+                self.send_tabline();
+            }
+            _ => {}
         }
     }
 
@@ -454,3 +760,59 @@ pub fn start_editor() {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn test_reset_state() {
+        let (batch_sender, batch_receiver) = channel();
+        let mut editor = Editor::new();
+        editor.draw_command_batcher = Arc::new(DrawCommandBatcher::with_batch_sender(batch_sender));
+
+        let events = vec![
+            RedrawEvent::Resize {
+                grid: 1,
+                width: 10,
+                height: 4,
+            },
+            RedrawEvent::HighlightAttributesDefine {
+                id: 1,
+                style: Style::new(Colors::new(None, None, None)),
+            },
+            RedrawEvent::HighlightGroupSet {
+                name: "Pmenu".to_string(),
+                id: 1,
+            },
+        ];
+        for event in events {
+            editor.handle_editor_command(EditorCommand::NeovimRedrawEvent(event));
+        }
+        editor.tabs.push(TabInfo {
+            tab: 1,
+            name: "[No Name]".to_string(),
+        });
+        assert!(editor.windows.contains_key(&1));
+        batch_receiver.try_iter().for_each(drop);
+
+        editor.handle_editor_command(EditorCommand::ConnectionRestored);
+
+        assert!(editor.windows.is_empty());
+        assert!(editor.defined_styles.is_empty());
+        assert!(editor.highlight_groups.is_empty());
+        assert!(editor.tabs.is_empty());
+        let draw_commands: Vec<DrawCommand> = batch_receiver.try_iter().flatten().collect();
+        assert!(draw_commands
+            .iter()
+            .any(|command| matches!(command, DrawCommand::CloseWindow(1))));
+        assert!(draw_commands
+            .iter()
+            .any(|command| matches!(command, DrawCommand::PopupMenuHide)));
+        assert!(draw_commands
+            .iter()
+            .any(|command| matches!(command, DrawCommand::ConnectionStatus(None))));
+    }
+}
@@ -1,4 +1,9 @@
+use std::{collections::HashMap, sync::Arc};
+
 use skia_safe::Color4f;
+use unicode_width::UnicodeWidthStr;
+
+use crate::bridge::StyledContent;
 
 #[derive(new, PartialEq, Debug, Clone)]
 pub struct Colors {
@@ -7,6 +12,15 @@ pub struct Colors {
     pub special: Option<Color4f>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnderlineStyle {
+    Underline,
+    UnderDouble,
+    UnderCurl,
+    UnderDotted,
+    UnderDashed,
+}
+
 #[derive(new, Debug, Clone, PartialEq)]
 pub struct Style {
     pub colors: Colors,
@@ -19,11 +33,11 @@ pub struct Style {
     #[new(default)]
     pub strikethrough: bool,
     #[new(default)]
-    pub underline: bool,
-    #[new(default)]
-    pub undercurl: bool,
+    pub underline: Option<UnderlineStyle>,
     #[new(default)]
     pub blend: u8,
+    #[new(default)]
+    pub url: Option<String>,
 }
 
 impl Style {
@@ -56,6 +70,34 @@ impl Style {
             .special
             .unwrap_or_else(|| self.foreground(default_colors))
     }
+
+    // The opacity of the background, from the blend percentage where 100 is fully transparent
+    pub fn blend_alpha(&self) -> u8 {
+        blend_to_alpha(self.blend as u64)
+    }
+}
+
+// Converts a blend percentage as used by 'blend', 'winblend' and 'pumblend' to an alpha value
+pub fn blend_to_alpha(blend: u64) -> u8 {
+    (255 * (100 - blend.min(100)) / 100) as u8
+}
+
+// Text made of chunks with their highlight styles resolved, where None is the default style
+pub type StyledText = Vec<(Option<Arc<Style>>, String)>;
+
+pub fn resolve_styles(
+    content: StyledContent,
+    defined_styles: &HashMap<u64, Arc<Style>>,
+) -> StyledText {
+    content
+        .into_iter()
+        .map(|(style_id, text)| (defined_styles.get(&style_id).cloned(), text))
+        .collect()
+}
+
+// Width in cells, with wide characters taking up two
+pub fn styled_text_width(text: &StyledText) -> usize {
+    text.iter().map(|(_, text)| text.width()).sum()
 }
 
 #[cfg(test)]
@@ -147,4 +189,17 @@ mod tests {
             style.foreground(&DEFAULT_COLORS),
         );
     }
+
+    #[test]
+    fn test_blend_alpha() {
+        let mut style = Style::new(COLORS);
+        assert_eq!(style.blend_alpha(), 255);
+
+        style.blend = 20;
+        assert_eq!(style.blend_alpha(), 204);
+
+        style.blend = 100;
+        assert_eq!(style.blend_alpha(), 0);
+        assert_eq!(blend_to_alpha(150), 0);
+    }
 }
@@ -5,10 +5,37 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     bridge::GridLineCell,
-    editor::{grid::CharacterGrid, style::Style, AnchorInfo, DrawCommand, DrawCommandBatcher},
-    renderer::{LineFragment, WindowDrawCommand},
+    editor::{
+        grid::{CharacterGrid, GridCell},
+        style::Style,
+        AnchorInfo, DrawCommand, DrawCommandBatcher,
+    },
+    renderer::{LineFragment, Link, WindowDrawCommand},
 };
 
+// Finds the runs of cells whose highlight carries a url, such as OSC 8 links in terminal buffers
+fn find_links(row_index: u64, row: &[GridCell]) -> Vec<Link> {
+    let mut links: Vec<Link> = Vec::new();
+    for (column, (_, style)) in row.iter().enumerate() {
+        let column = column as u64;
+        let url = match style.as_ref().and_then(|style| style.url.as_ref()) {
+            Some(url) => url,
+            None => continue,
+        };
+
+        match links.last_mut() {
+            Some(link) if link.end == column && &link.url == url => link.end += 1,
+            _ => links.push(Link {
+                row: row_index,
+                start: column,
+                end: column + 1,
+                url: url.clone(),
+            }),
+        }
+    }
+    links
+}
+
 pub enum WindowType {
     Editor,
     Message,
@@ -21,6 +48,7 @@ pub struct Window {
 
     pub anchor_info: Option<AnchorInfo>,
     grid_position: (f64, f64),
+    links: Vec<Link>,
 
     draw_command_batcher: Arc<DrawCommandBatcher>,
 }
@@ -40,6 +68,7 @@ impl Window {
             window_type,
             anchor_info,
             grid_position,
+            links: Vec::new(),
             draw_command_batcher,
         };
         window.send_updated_position();
@@ -104,12 +133,43 @@ impl Window {
         self.grid_position = grid_position;
         self.send_updated_position();
         self.redraw();
+        self.update_all_links();
     }
 
     pub fn resize(&mut self, new_size: (u64, u64)) {
         self.grid.resize(new_size);
         self.send_updated_position();
         self.redraw();
+        self.update_all_links();
+    }
+
+    // Links are only sent when they change, which keeps grids without any of them free of the
+    // extra work
+    fn update_row_links(&mut self, row: u64) {
+        let row_links = self
+            .grid
+            .row(row)
+            .map(|cells| find_links(row, cells))
+            .unwrap_or_default();
+        if row_links.is_empty() && !self.links.iter().any(|link| link.row == row) {
+            return;
+        }
+
+        self.links.retain(|link| link.row != row);
+        self.links.extend(row_links);
+        self.send_command(WindowDrawCommand::Links(self.links.clone()));
+    }
+
+    fn update_all_links(&mut self) {
+        if self.links.is_empty() {
+            return;
+        }
+
+        self.links = (0..self.grid.height)
+            .filter_map(|row| self.grid.row(row).map(|cells| find_links(row, cells)))
+            .flatten()
+            .collect();
+        self.send_command(WindowDrawCommand::Links(self.links.clone()));
     }
 
     fn modify_grid(
@@ -226,6 +286,7 @@ impl Window {
             }
 
             self.redraw_line(row);
+            self.update_row_links(row);
         } else {
             warn!("Draw command out of bounds");
         }
@@ -288,11 +349,14 @@ impl Window {
                 }
             }
         }
+
+        self.update_all_links();
     }
 
     pub fn clear(&mut self) {
         self.grid.clear();
         self.send_command(WindowDrawCommand::Clear);
+        self.update_all_links();
     }
 
     pub fn redraw(&self) {
@@ -327,7 +391,7 @@ mod tests {
     use std::collections::HashMap;
 
     use super::*;
-    use crate::event_aggregator::EVENT_AGGREGATOR;
+    use crate::{editor::style::Colors, event_aggregator::EVENT_AGGREGATOR};
 
     #[test]
     fn window_separator_modifies_grid_and_sends_draw_command() {
@@ -369,4 +433,36 @@ mod tests {
             .expect("Could not receive commands");
         assert!(!sent_commands.is_empty());
     }
+
+    #[test]
+    fn find_links_groups_cells_by_url() {
+        let link_style = |url: &str| {
+            let mut style = Style::new(Colors::new(None, None, None));
+            style.url = Some(url.to_owned());
+            Some(Arc::new(style))
+        };
+        let row: Vec<GridCell> = vec![
+            ("a".to_owned(), None),
+            ("b".to_owned(), link_style("https://neovide.dev")),
+            ("c".to_owned(), link_style("https://neovide.dev")),
+            ("d".to_owned(), link_style("https://neovim.io")),
+            ("e".to_owned(), None),
+            ("f".to_owned(), link_style("https://neovide.dev")),
+        ];
+
+        let links = find_links(3, &row);
+        let spans: Vec<(u64, u64, &str)> = links
+            .iter()
+            .map(|link| (link.start, link.end, link.url.as_str()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (1, 3, "https://neovide.dev"),
+                (3, 4, "https://neovim.io"),
+                (5, 6, "https://neovide.dev"),
+            ]
+        );
+        assert!(links.iter().all(|link| link.row == 3));
+    }
 }
@@ -1,6 +1,6 @@
 use log::error;
 
-fn show_error(explanation: &str) -> ! {
+pub fn show_error(explanation: &str) -> ! {
     error!("{}", explanation);
     panic!("{}", explanation.to_string());
 }
@@ -20,6 +20,7 @@ mod redraw_scheduler;
 mod renderer;
 mod running_tracker;
 mod settings;
+mod single_instance;
 mod window;
 
 #[cfg(target_os = "windows")]
@@ -30,7 +31,7 @@ extern crate derive_new;
 #[macro_use]
 extern crate lazy_static;
 
-use std::env::args;
+use std::{env::args, path::Path};
 
 #[cfg(not(test))]
 use flexi_logger::{Cleanup, Criterion, Duplicate, FileSpec, Logger, Naming};
@@ -41,7 +42,7 @@ use cmd_line::CmdLineSettings;
 use editor::start_editor;
 use renderer::{cursor_renderer::CursorSettings, RendererSettings};
 use settings::SETTINGS;
-use window::{create_window, KeyboardSettings, WindowSettings};
+use window::{create_window, run_headless, KeyboardSettings, WindowSettings};
 
 pub use channel_utils::*;
 pub use event_aggregator::*;
@@ -129,6 +130,12 @@ fn main() {
 
     trace!("Neovide version: {}", crate_version!());
 
+    if SETTINGS.get::<CmdLineSettings>().single_instance
+        && single_instance::forward_to_running_instance()
+    {
+        return;
+    }
+
     maybe_disown();
 
     #[cfg(target_os = "windows")]
@@ -141,7 +148,12 @@ fn main() {
 
     start_bridge();
     start_editor();
-    create_window();
+
+    if let Some(output_dir) = SETTINGS.get::<CmdLineSettings>().headless {
+        run_headless(Path::new(&output_dir));
+    } else {
+        create_window();
+    }
 }
 
 #[cfg(not(test))]
@@ -170,7 +182,7 @@ fn maybe_disown() {
 
     let settings = SETTINGS.get::<CmdLineSettings>();
 
-    if cfg!(debug_assertions) || settings.no_fork {
+    if cfg!(debug_assertions) || settings.no_fork || settings.headless.is_some() {
         return;
     }
 
@@ -1,4 +1,4 @@
-use skia_safe::Point;
+use skia_safe::{Color4f, Point};
 
 #[allow(dead_code)]
 pub fn ease_linear(t: f32) -> f32 {
@@ -80,6 +80,15 @@ pub fn ease_point(ease_func: fn(f32) -> f32, start: Point, end: Point, t: f32) -
     }
 }
 
+pub fn lerp_color(start: Color4f, end: Color4f, t: f32) -> Color4f {
+    Color4f::new(
+        lerp(start.r, end.r, t),
+        lerp(start.g, end.g, t),
+        lerp(start.b, end.b, t),
+        lerp(start.a, end.a, t),
+    )
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,107 @@
+use skia_safe::{paint::Style as PaintStyle, Canvas, Paint, Point, RRect, Rect};
+
+use crate::{
+    editor::{styled_text_width, CommandLine, StyledText},
+    renderer::{animation_utils::lerp_color, GridRenderer},
+};
+
+// Sizes in logical pixels, scaled by the window scale factor when drawn
+const PADDING: f32 = 8.0;
+const CORNER_RADIUS: f32 = 6.0;
+const BORDER_WIDTH: f32 = 1.0;
+// The widget is at least this fraction of the window wide, and starts this far down the window
+const MIN_WIDTH_RATIO: f32 = 0.5;
+const TOP_RATIO: f32 = 0.2;
+
+// Draws the command line as a floating widget centered horizontally near the top of the window.
+// Returns the top left corner of the line being edited, which command line completions are
+// anchored to.
+pub fn draw_command_line(
+    canvas: &mut Canvas,
+    command_line: &CommandLine,
+    grid_renderer: &mut GridRenderer,
+) -> Option<Point> {
+    if !command_line.is_visible() {
+        return None;
+    }
+
+    let font_width = grid_renderer.font_dimensions.width as f32;
+    let font_height = grid_renderer.font_dimensions.height as f32;
+    let scale_factor = grid_renderer.scale_factor as f32;
+    let padding = PADDING * scale_factor;
+
+    let level = command_line.levels.last();
+    let line_width = level
+        .map(|level| level.prefix_width() + styled_text_width(&level.displayed_content()) + 1)
+        .unwrap_or(0);
+    let columns = command_line
+        .block
+        .iter()
+        .map(styled_text_width)
+        .fold(line_width, usize::max);
+    let rows = command_line.block.len() + level.map_or(0, |_| 1);
+
+    let canvas_size = canvas.base_layer_size();
+    let canvas_width = canvas_size.width as f32;
+    let width = (columns as f32 * font_width + padding * 2.0)
+        .max(canvas_width * MIN_WIDTH_RATIO)
+        .min(canvas_width);
+    let height = rows as f32 * font_height + padding * 2.0;
+    let left = (canvas_width - width) / 2.0;
+    let top = (canvas_size.height as f32 * TOP_RATIO).floor();
+
+    let colors = &grid_renderer.default_style.colors;
+    let foreground = colors.foreground.unwrap();
+    let background = colors.background.unwrap();
+
+    let region = RRect::new_rect_xy(
+        Rect::from_xywh(left, top, width, height),
+        CORNER_RADIUS * scale_factor,
+        CORNER_RADIUS * scale_factor,
+    );
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(lerp_color(background, foreground, 0.08).to_color());
+    canvas.draw_rrect(region, &paint);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(BORDER_WIDTH * scale_factor);
+    paint.set_color(lerp_color(background, foreground, 0.3).to_color());
+    canvas.draw_rrect(region, &paint);
+
+    canvas.save();
+    canvas.clip_rrect(region, None, Some(true));
+
+    let text_left = left + padding;
+    let mut y = top + padding;
+    for line in &command_line.block {
+        grid_renderer.draw_styled_text(canvas, line, (text_left, y));
+        y += font_height;
+    }
+
+    let origin = level.map(|level| {
+        let prefix: StyledText = vec![(
+            None,
+            format!(
+                "{}{}{}",
+                level.first_character,
+                level.prompt,
+                " ".repeat(level.indent as usize)
+            ),
+        )];
+        let x = grid_renderer.draw_styled_text(canvas, &prefix, (text_left, y));
+        grid_renderer.draw_styled_text(canvas, &level.displayed_content(), (x, y));
+
+        let cursor_x = text_left + level.cursor_column() as f32 * font_width;
+        let mut cursor_paint = Paint::default();
+        cursor_paint.set_color(foreground.to_color());
+        canvas.draw_rect(
+            Rect::from_xywh(cursor_x, y, (font_width / 8.0).max(1.0), font_height),
+            &cursor_paint,
+        );
+
+        Point::new(text_left, y)
+    });
+
+    canvas.restore();
+    origin
+}
@@ -1,4 +1,3 @@
-use log::error;
 use nvim_rs::Value;
 use skia_safe::{paint::Style, BlendMode, Canvas, Color, Paint, Point, Rect};
 
@@ -49,24 +48,23 @@ pub enum VfxMode {
 }
 
 impl ParseFromValue for VfxMode {
-    fn parse_from_value(&mut self, value: Value) {
-        if value.is_str() {
-            *self = match value.as_str().unwrap() {
-                "sonicboom" => VfxMode::Highlight(HighlightMode::SonicBoom),
-                "ripple" => VfxMode::Highlight(HighlightMode::Ripple),
-                "wireframe" => VfxMode::Highlight(HighlightMode::Wireframe),
-                "railgun" => VfxMode::Trail(TrailMode::Railgun),
-                "torpedo" => VfxMode::Trail(TrailMode::Torpedo),
-                "pixiedust" => VfxMode::Trail(TrailMode::PixieDust),
-                "" => VfxMode::Disabled,
-                value => {
-                    error!("Expected a VfxMode name, but received {:?}", value);
-                    return;
-                }
-            };
-        } else {
-            error!("Expected a VfxMode string, but received {:?}", value);
-        }
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
+        *self = match value.as_str() {
+            Some("sonicboom") => VfxMode::Highlight(HighlightMode::SonicBoom),
+            Some("ripple") => VfxMode::Highlight(HighlightMode::Ripple),
+            Some("wireframe") => VfxMode::Highlight(HighlightMode::Wireframe),
+            Some("railgun") => VfxMode::Trail(TrailMode::Railgun),
+            Some("torpedo") => VfxMode::Trail(TrailMode::Torpedo),
+            Some("pixiedust") => VfxMode::Trail(TrailMode::PixieDust),
+            Some("") => VfxMode::Disabled,
+            _ => {
+                return Err(format!(
+                    "expected one of \"sonicboom\", \"ripple\", \"wireframe\", \"railgun\", \"torpedo\", \"pixiedust\" or \"\", but received {}",
+                    value
+                ))
+            }
+        };
+        Ok(())
     }
 }
 
@@ -15,6 +15,7 @@ use swash::{
     Metrics,
 };
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::renderer::fonts::{font_loader::*, font_options::*};
 
@@ -27,11 +28,42 @@ struct ShapeKey {
 
 pub struct CachingShaper {
     options: FontOptions,
+    // Fonts from 'guifontwide', tried first for characters taking two cells
+    wide_font_list: Vec<String>,
     font_loader: FontLoader,
     blob_cache: LruCache<ShapeKey, Vec<TextBlob>>,
     shape_context: ShapeContext,
     scale_factor: f32,
     fudge_factor: f32,
+    linespace: f32,
+}
+
+// Create font fallback list for a cluster of characters
+fn font_fallback_keys(
+    options: &FontOptions,
+    wide_font_list: &[String],
+    cluster_text: &str,
+    bold: bool,
+    italic: bool,
+) -> Vec<FontKey> {
+    let font_key = |family_name: Option<String>| FontKey {
+        italic: options.italic || italic,
+        bold: options.bold || bold,
+        family_name,
+    };
+    let mut font_fallback_keys = Vec::new();
+
+    // Add parsed fonts from guifontwide for clusters spanning two cells
+    if cluster_text.width() > 1 {
+        font_fallback_keys.extend(wide_font_list.iter().cloned().map(Some).map(font_key));
+    }
+
+    // Add parsed fonts from guifont
+    font_fallback_keys.extend(options.font_list.iter().cloned().map(Some).map(font_key));
+
+    // Add default font
+    font_fallback_keys.push(font_key(None));
+    font_fallback_keys
 }
 
 impl CachingShaper {
@@ -40,11 +72,13 @@ impl CachingShaper {
         let font_size = options.size * scale_factor;
         let mut shaper = CachingShaper {
             options,
+            wide_font_list: Vec::new(),
             font_loader: FontLoader::new(font_size),
             blob_cache: LruCache::new(10000),
             shape_context: ShapeContext::new(),
             scale_factor,
             fudge_factor: 1.0,
+            linespace: 0.0,
         };
         shaper.reset_font_loader();
         shaper
@@ -74,6 +108,16 @@ impl CachingShaper {
         self.reset_font_loader();
     }
 
+    // Extra space in logical pixels added to the height of every line, which may be negative
+    pub fn update_linespace(&mut self, linespace: f32) {
+        debug!("linespace changed: {:.2}", linespace);
+        self.linespace = linespace;
+    }
+
+    fn scaled_linespace(&self) -> f32 {
+        self.linespace * self.scale_factor
+    }
+
     pub fn update_font(&mut self, guifont_setting: &str) {
         debug!("Updating font: {}", guifont_setting);
 
@@ -93,6 +137,14 @@ impl CachingShaper {
         }
     }
 
+    // 'guifontwide' uses the format of 'guifont', but only the font names are used since wide
+    // characters are drawn at the size of the regular font
+    pub fn update_font_wide(&mut self, guifontwide_setting: &str) {
+        debug!("Updating wide font: {}", guifontwide_setting);
+        self.wide_font_list = FontOptions::parse(guifontwide_setting).font_list;
+        self.blob_cache.clear();
+    }
+
     fn reset_font_loader(&mut self) {
         self.fudge_factor = 1.0;
         let mut font_size = self.current_size();
@@ -149,19 +201,30 @@ impl CachingShaper {
 
     pub fn font_base_dimensions(&mut self) -> (u64, u64) {
         let (metrics, glyph_advance) = self.info();
-        let font_height = (metrics.ascent + metrics.descent + metrics.leading).ceil() as u64;
+        let font_height =
+            (metrics.ascent + metrics.descent + metrics.leading + self.scaled_linespace())
+                .ceil()
+                .max(1.0) as u64;
         let font_width = (glyph_advance + 0.5).floor() as u64;
 
         (font_width, font_height)
     }
 
-    pub fn underline_position(&mut self) -> u64 {
-        self.metrics().underline_offset as u64
+    // The top of the underline relative to the top of the cell, and its thickness, both taken from
+    // the font. The offset is measured from the baseline and is negative below it.
+    pub fn underline_metrics(&mut self) -> (f32, f32) {
+        let metrics = self.metrics();
+        let stroke_size = metrics.stroke_size.max(1.0);
+        let position = self.y_adjustment() as f32 - metrics.underline_offset;
+        (position, stroke_size)
     }
 
     pub fn y_adjustment(&mut self) -> u64 {
+        // Half of the linespace goes above the text, which keeps it vertically centered
         let metrics = self.metrics();
-        (metrics.ascent + metrics.leading).ceil() as u64
+        (metrics.ascent + metrics.leading + self.scaled_linespace() / 2.0)
+            .ceil()
+            .max(0.0) as u64
     }
 
     fn build_clusters(
@@ -197,22 +260,14 @@ impl CachingShaper {
         let mut results = Vec::new();
         'cluster: while parser.next(&mut cluster) {
             // TODO: Don't redo this work for every cluster. Save it some how
-            // Create font fallback list
-            let mut font_fallback_keys = Vec::new();
-
-            // Add parsed fonts from guifont
-            font_fallback_keys.extend(self.options.font_list.iter().map(|font_name| FontKey {
-                italic: self.options.italic || italic,
-                bold: self.options.bold || bold,
-                family_name: Some(font_name.clone()),
-            }));
-
-            // Add default font
-            font_fallback_keys.push(FontKey {
-                italic: self.options.italic || italic,
-                bold: self.options.bold || bold,
-                family_name: None,
-            });
+            let cluster_text: String = cluster.chars().iter().map(|c| c.ch).collect();
+            let font_fallback_keys = font_fallback_keys(
+                &self.options,
+                &self.wide_font_list,
+                &cluster_text,
+                bold,
+                italic,
+            );
 
             // Use the cluster.map function to select a viable font from the fallback list and loaded fonts
 
@@ -366,3 +421,72 @@ impl CachingShaper {
         self.blob_cache.get(&key).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family_names(keys: Vec<FontKey>) -> Vec<Option<String>> {
+        keys.into_iter().map(|key| key.family_name).collect()
+    }
+
+    #[test]
+    fn test_wide_characters_prefer_guifontwide() {
+        let options = FontOptions::parse("Fira Code,Noto Sans:h12");
+        let wide_font_list = FontOptions::parse("Noto Sans CJK JP:h12").font_list;
+
+        assert_eq!(
+            family_names(font_fallback_keys(
+                &options,
+                &wide_font_list,
+                "字",
+                false,
+                false
+            )),
+            vec![
+                Some("Noto Sans CJK JP".to_string()),
+                Some("Fira Code".to_string()),
+                Some("Noto Sans".to_string()),
+                None,
+            ]
+        );
+
+        // Characters taking a single cell only use guifont
+        assert_eq!(
+            family_names(font_fallback_keys(
+                &options,
+                &wide_font_list,
+                "a",
+                false,
+                false
+            )),
+            vec![
+                Some("Fira Code".to_string()),
+                Some("Noto Sans".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wide_characters_fall_back_to_guifont() {
+        let options = FontOptions::parse("Fira Code:h12:b");
+        let keys = font_fallback_keys(&options, &[], "字", false, true);
+
+        assert_eq!(
+            keys,
+            vec![
+                FontKey {
+                    bold: true,
+                    italic: true,
+                    family_name: Some("Fira Code".to_string()),
+                },
+                FontKey {
+                    bold: true,
+                    italic: true,
+                    family_name: None,
+                },
+            ]
+        );
+    }
+}
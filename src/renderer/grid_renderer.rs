@@ -1,12 +1,16 @@
-use std::sync::Arc;
+use std::{f32::consts::PI, sync::Arc};
 
 use glutin::dpi::PhysicalSize;
 use log::trace;
-use skia_safe::{colors, dash_path_effect, BlendMode, Canvas, Color, Paint, Rect, HSV};
+use skia_safe::{
+    colors, dash_path_effect, paint::Style as PaintStyle, BlendMode, Canvas, Color, Paint, Path,
+    Rect, HSV,
+};
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
     dimensions::Dimensions,
-    editor::{Colors, Style},
+    editor::{Colors, Style, StyledText, UnderlineStyle},
     renderer::{CachingShaper, RendererSettings},
     settings::*,
     window::WindowSettings,
@@ -61,6 +65,7 @@ impl GridRenderer {
     }
 
     pub fn handle_scale_factor_update(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
         self.shaper.update_scale_factor(scale_factor as f32);
         self.update_font_dimensions();
     }
@@ -70,6 +75,15 @@ impl GridRenderer {
         self.update_font_dimensions();
     }
 
+    pub fn update_font_wide(&mut self, guifontwide_setting: &str) {
+        self.shaper.update_font_wide(guifontwide_setting);
+    }
+
+    pub fn update_linespace(&mut self, linespace: f32) {
+        self.shaper.update_linespace(linespace);
+        self.update_font_dimensions();
+    }
+
     fn update_font_dimensions(&mut self) {
         self.em_size = self.shaper.current_size();
         self.font_dimensions = self.shaper.font_base_dimensions().into();
@@ -110,7 +124,11 @@ impl GridRenderer {
                 .set_color(style.background(&self.default_style.colors).to_color());
         }
 
-        if is_floating {
+        if style.blend > 0 {
+            // Blended cells let the windows below show through, like the TUI compositor does. This
+            // is also how floating windows get their 'winblend'.
+            self.paint.set_alpha(style.blend_alpha());
+        } else if is_floating {
             self.paint
                 .set_alpha((255.0 * SETTINGS.get::<RendererSettings>().floating_opacity) as u8);
         } else if (SETTINGS.get::<WindowSettings>().transparency - 1.0).abs() > f32::EPSILON
@@ -145,34 +163,18 @@ impl GridRenderer {
 
         canvas.clip_rect(region, None, Some(false));
 
-        if style.underline || style.undercurl {
-            let mut underline_paint = self.paint.clone();
-
-            let line_position = self.shaper.underline_position();
-            let stroke_width = self.shaper.current_size() / 10.0;
-
-            underline_paint.set_color(style.special(&self.default_style.colors).to_color());
-            underline_paint.set_stroke_width(stroke_width);
-
-            if style.undercurl {
-                underline_paint.set_path_effect(dash_path_effect::new(
-                    &[stroke_width * 2.0, stroke_width * 2.0],
-                    0.0,
-                ));
-            } else {
-                underline_paint.set_path_effect(None);
+        if let Some(underline_style) = style.underline {
+            let mut color = style.special(&self.default_style.colors).to_color();
+            // Underlines of blended highlights are as translucent as their background
+            if style.blend > 0 {
+                color = color.with_a(style.blend_alpha());
             }
-
-            canvas.draw_line(
-                (
-                    x as f32,
-                    (y - line_position + self.font_dimensions.height) as f32,
-                ),
-                (
-                    (x + width) as f32,
-                    (y - line_position + self.font_dimensions.height) as f32,
-                ),
-                &underline_paint,
+            self.draw_underline(
+                canvas,
+                underline_style,
+                color,
+                (x as f32, y as f32),
+                width as f32,
             );
         }
 
@@ -209,4 +211,125 @@ impl GridRenderer {
 
         canvas.restore();
     }
+
+    fn draw_underline(
+        &mut self,
+        canvas: &mut Canvas,
+        underline_style: UnderlineStyle,
+        color: Color,
+        (x, y): (f32, f32),
+        width: f32,
+    ) {
+        let font_width = self.font_dimensions.width as f32;
+        let font_height = self.font_dimensions.height as f32;
+        let (position, stroke_width) = self.shaper.underline_metrics();
+
+        // Based on the cell paint so its blend mode carries over, as for the text itself
+        let mut paint = self.paint.clone();
+        paint.set_color(color);
+        paint.set_path_effect(None);
+        paint.set_stroke_width(stroke_width);
+        paint.set_style(PaintStyle::Stroke);
+
+        // Lines are drawn through their center, and have to stay inside the cell
+        let fit_in_cell = |offset: f32| {
+            (position + offset + stroke_width / 2.0).min(font_height - stroke_width / 2.0)
+        };
+        let line_y = y + fit_in_cell(0.0);
+        let draw_line = |canvas: &mut Canvas, line_y: f32, paint: &Paint| {
+            canvas.draw_line((x, line_y), (x + width, line_y), paint);
+        };
+
+        match underline_style {
+            UnderlineStyle::Underline => draw_line(canvas, line_y, &paint),
+            UnderlineStyle::UnderDouble => {
+                let second_y = y + fit_in_cell(stroke_width * 2.0);
+                draw_line(canvas, second_y - stroke_width * 2.0, &paint);
+                draw_line(canvas, second_y, &paint);
+            }
+            UnderlineStyle::UnderDotted => {
+                paint.set_path_effect(dash_path_effect::new(&[stroke_width, stroke_width], 0.0));
+                draw_line(canvas, line_y, &paint);
+            }
+            UnderlineStyle::UnderDashed => {
+                paint.set_path_effect(dash_path_effect::new(
+                    &[stroke_width * 4.0, stroke_width * 2.0],
+                    0.0,
+                ));
+                draw_line(canvas, line_y, &paint);
+            }
+            UnderlineStyle::UnderCurl => {
+                // One wave per cell, so the curl lines up across separately drawn fragments. The
+                // amplitude is limited by the room left below the baseline.
+                let amplitude = (stroke_width * 1.5)
+                    .min((font_height - position) / 2.0)
+                    .max(1.0);
+                let center_y = y + (position + amplitude).min(font_height - amplitude);
+
+                // Sampled every half pixel, which is smooth enough once antialiased
+                let samples = (width * 2.0).ceil() as usize;
+                let mut path = Path::new();
+                for sample in 0..=samples {
+                    let offset = sample as f32 / 2.0;
+                    let point = (
+                        x + offset,
+                        center_y - amplitude * (offset / font_width * 2.0 * PI).sin(),
+                    );
+                    if sample == 0 {
+                        path.move_to(point);
+                    } else {
+                        path.line_to(point);
+                    }
+                }
+
+                paint.set_anti_alias(true);
+                canvas.save();
+                canvas.clip_rect(Rect::from_xywh(x, y, width, font_height), None, Some(false));
+                canvas.draw_path(&path, &paint);
+                canvas.restore();
+            }
+        }
+    }
+
+    /// Draws styled text outside of any grid, such as in the ui extension widgets
+    ///
+    /// # Returns
+    /// The x position after the last drawn chunk
+    pub fn draw_styled_text(
+        &mut self,
+        canvas: &mut Canvas,
+        text: &StyledText,
+        (mut x, y): (f32, f32),
+    ) -> f32 {
+        let font_width = self.font_dimensions.width as f32;
+        let font_height = self.font_dimensions.height as f32;
+        let y_adjustment = self.shaper.y_adjustment() as f32;
+        let default_style = self.default_style.clone();
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+
+        for (style, chunk) in text {
+            let style = style.as_ref().unwrap_or(&default_style);
+            let chunk_width = chunk.width() as f32 * font_width;
+
+            if style.colors.background.is_some() || style.reverse {
+                paint.set_color(style.background(&default_style.colors).to_color());
+                canvas.draw_rect(Rect::from_xywh(x, y, chunk_width, font_height), &paint);
+            }
+
+            paint.set_color(style.foreground(&default_style.colors).to_color());
+            for blob in self
+                .shaper
+                .shape_cached(chunk.clone(), style.bold, style.italic)
+                .iter()
+            {
+                canvas.draw_text_blob(blob, (x, y + y_adjustment), &paint);
+            }
+
+            x += chunk_width;
+        }
+
+        x
+    }
 }
@@ -0,0 +1,424 @@
+use std::time::{Duration, Instant};
+
+use skia_safe::{Canvas, Color, Color4f, Paint, RRect, Rect};
+
+use crate::{
+    bridge::MessageKind,
+    editor::{styled_text_width, StyledText},
+    redraw_scheduler::REDRAW_SCHEDULER,
+    renderer::{animation_utils::lerp_color, GridRenderer},
+};
+
+const MAX_TOASTS: usize = 5;
+const TOAST_TIMEOUT: Duration = Duration::from_secs(4);
+const FADE_DURATION: Duration = Duration::from_millis(300);
+// Toasts are at most this fraction of the window wide, the history panel this fraction of its size
+const TOAST_MAX_WIDTH_RATIO: f32 = 0.5;
+const HISTORY_SIZE_RATIO: f32 = 0.7;
+// Sizes in logical pixels, scaled by the window scale factor when drawn
+const PADDING: f32 = 6.0;
+const MARGIN: f32 = 8.0;
+const CORNER_RADIUS: f32 = 6.0;
+const ACCENT_WIDTH: f32 = 3.0;
+const SCROLLBAR_WIDTH: f32 = 4.0;
+
+fn kind_color(kind: &MessageKind) -> Option<Color> {
+    match kind {
+        MessageKind::Error
+        | MessageKind::EchoError
+        | MessageKind::LuaError
+        | MessageKind::RpcError => Some(Color::from_rgb(0xe0, 0x6c, 0x75)),
+        MessageKind::Warning => Some(Color::from_rgb(0xd1, 0x9a, 0x66)),
+        MessageKind::Confirm | MessageKind::ConfirmSubstitute | MessageKind::ReturnPrompt => {
+            Some(Color::from_rgb(0x61, 0xaf, 0xef))
+        }
+        _ => None,
+    }
+}
+
+// Prompts wait for the user to answer, so they stay until neovim clears them
+fn is_persistent(kind: &MessageKind) -> bool {
+    matches!(
+        kind,
+        MessageKind::Confirm | MessageKind::ConfirmSubstitute | MessageKind::ReturnPrompt
+    )
+}
+
+fn is_subdued(kind: &MessageKind) -> bool {
+    matches!(kind, MessageKind::SearchCount)
+}
+
+// Messages can span several lines, with the line breaks anywhere inside the styled chunks
+fn split_lines(text: StyledText) -> Vec<StyledText> {
+    let mut lines = vec![Vec::new()];
+    for (style, chunk) in text {
+        for (index, part) in chunk.split('\n').enumerate() {
+            if index > 0 {
+                lines.push(Vec::new());
+            }
+            if !part.is_empty() {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push((style.clone(), part.to_owned()));
+            }
+        }
+    }
+
+    while lines.len() > 1 && lines.last().map_or(false, Vec::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+struct Toast {
+    kind: MessageKind,
+    lines: Vec<StyledText>,
+    shown_at: Instant,
+}
+
+impl Toast {
+    fn new(kind: MessageKind, content: StyledText) -> Toast {
+        Toast {
+            kind,
+            lines: split_lines(content),
+            shown_at: Instant::now(),
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        !is_persistent(&self.kind) && now.duration_since(self.shown_at) >= TOAST_TIMEOUT
+    }
+
+    // Fully opaque until the last moments before expiring, then fades out
+    fn opacity(&self, now: Instant) -> f32 {
+        if is_persistent(&self.kind) {
+            return 1.0;
+        }
+
+        let remaining = TOAST_TIMEOUT
+            .checked_sub(now.duration_since(self.shown_at))
+            .unwrap_or_default();
+        (remaining.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0)
+    }
+}
+
+struct MessageHistory {
+    lines: Vec<(MessageKind, StyledText)>,
+    scroll_offset: usize,
+}
+
+#[derive(Default)]
+pub struct Messages {
+    toasts: Vec<Toast>,
+    history: Option<MessageHistory>,
+    history_rows: usize,
+    pub mode: StyledText,
+    pub command: StyledText,
+    pub ruler: StyledText,
+}
+
+impl Messages {
+    pub fn show(&mut self, kind: MessageKind, content: StyledText, replace_last: bool) {
+        if replace_last {
+            self.toasts.pop();
+        }
+
+        self.toasts.push(Toast::new(kind, content));
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    // Neovim clears the messages whenever the screen is redrawn, which would hide most toasts
+    // before they could be read. Only prompts are removed, the others expire on their own.
+    pub fn clear(&mut self) {
+        self.toasts.retain(|toast| !is_persistent(&toast.kind));
+        self.history = None;
+    }
+
+    pub fn show_history(&mut self, entries: Vec<(MessageKind, StyledText)>) {
+        if entries.is_empty() {
+            self.history = None;
+            return;
+        }
+
+        let lines: Vec<(MessageKind, StyledText)> = entries
+            .into_iter()
+            .flat_map(|(kind, content)| {
+                split_lines(content)
+                    .into_iter()
+                    .map(move |line| (kind.clone(), line))
+            })
+            .collect();
+        // Start at the most recent messages
+        let scroll_offset = lines.len().saturating_sub(self.history_rows.max(1));
+        self.history = Some(MessageHistory {
+            lines,
+            scroll_offset,
+        });
+    }
+
+    pub fn is_history_visible(&self) -> bool {
+        self.history.is_some()
+    }
+
+    // Scrolls the history panel by a number of lines, positive towards older messages
+    pub fn scroll_history(&mut self, lines: i64) {
+        let rows = self.history_rows.max(1);
+        if let Some(history) = &mut self.history {
+            let max_offset = history.lines.len().saturating_sub(rows) as i64;
+            history.scroll_offset =
+                (history.scroll_offset as i64 - lines).clamp(0, max_offset) as usize;
+        }
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas, grid_renderer: &mut GridRenderer) {
+        let now = Instant::now();
+        self.toasts.retain(|toast| !toast.is_expired(now));
+
+        self.draw_toasts(canvas, grid_renderer, now);
+        self.draw_status(canvas, grid_renderer);
+        self.draw_history(canvas, grid_renderer);
+
+        // Keep drawing frames until every toast has faded out
+        if self.toasts.iter().any(|toast| !is_persistent(&toast.kind)) {
+            REDRAW_SCHEDULER.queue_next_frame();
+        }
+    }
+
+    fn draw_toasts(&self, canvas: &mut Canvas, grid_renderer: &mut GridRenderer, now: Instant) {
+        let font_width = grid_renderer.font_dimensions.width as f32;
+        let font_height = grid_renderer.font_dimensions.height as f32;
+        let scale_factor = grid_renderer.scale_factor as f32;
+        let padding = PADDING * scale_factor;
+        let margin = MARGIN * scale_factor;
+        let accent_width = ACCENT_WIDTH * scale_factor;
+        let canvas_width = canvas.base_layer_size().width as f32;
+        let (foreground, background) = default_colors(grid_renderer);
+
+        let mut top = margin;
+        for toast in &self.toasts {
+            let columns = toast.lines.iter().map(styled_text_width).max().unwrap_or(0);
+            let width = (columns as f32 * font_width + padding * 2.0 + accent_width)
+                .min(canvas_width * TOAST_MAX_WIDTH_RATIO);
+            let height = toast.lines.len() as f32 * font_height + padding * 2.0;
+            let region = Rect::from_xywh(canvas_width - margin - width, top, width, height);
+
+            let mut opacity = toast.opacity(now);
+            if is_subdued(&toast.kind) {
+                opacity *= 0.6;
+            }
+            canvas.save_layer_alpha(region, (opacity * 255.0) as u32);
+
+            let rounded_region = draw_panel(canvas, region, scale_factor, foreground, background);
+            canvas.clip_rrect(rounded_region, None, Some(true));
+
+            if let Some(color) = kind_color(&toast.kind) {
+                let mut paint = Paint::default();
+                paint.set_color(color);
+                canvas.draw_rect(
+                    Rect::from_xywh(region.left, top, accent_width, height),
+                    &paint,
+                );
+            }
+
+            let text_left = region.left + accent_width + padding;
+            for (row, line) in toast.lines.iter().enumerate() {
+                let y = top + padding + row as f32 * font_height;
+                grid_renderer.draw_styled_text(canvas, line, (text_left, y));
+            }
+
+            canvas.restore();
+            top += height + margin;
+        }
+    }
+
+    // Shows the mode, the pending command and the ruler in the bottom right corner, where neovim
+    // would draw them without ext_messages
+    fn draw_status(&self, canvas: &mut Canvas, grid_renderer: &mut GridRenderer) {
+        let parts: Vec<&StyledText> = [&self.mode, &self.command, &self.ruler]
+            .iter()
+            .copied()
+            .filter(|part| styled_text_width(part) > 0)
+            .collect();
+        if parts.is_empty() {
+            return;
+        }
+
+        let font_width = grid_renderer.font_dimensions.width as f32;
+        let font_height = grid_renderer.font_dimensions.height as f32;
+        let scale_factor = grid_renderer.scale_factor as f32;
+        let padding = PADDING * scale_factor;
+        let margin = MARGIN * scale_factor;
+        let (foreground, background) = default_colors(grid_renderer);
+
+        let columns: usize = parts
+            .iter()
+            .map(|part| styled_text_width(part))
+            .sum::<usize>()
+            + parts.len()
+            - 1;
+        let width = columns as f32 * font_width + padding * 2.0;
+        let height = font_height + padding * 2.0;
+        let canvas_size = canvas.base_layer_size();
+        let region = Rect::from_xywh(
+            canvas_size.width as f32 - margin - width,
+            canvas_size.height as f32 - margin - height,
+            width,
+            height,
+        );
+
+        draw_panel(canvas, region, scale_factor, foreground, background);
+        let mut x = region.left + padding;
+        for part in parts {
+            x = grid_renderer.draw_styled_text(canvas, part, (x, region.top + padding));
+            x += font_width;
+        }
+    }
+
+    fn draw_history(&mut self, canvas: &mut Canvas, grid_renderer: &mut GridRenderer) {
+        let font_height = grid_renderer.font_dimensions.height as f32;
+        let scale_factor = grid_renderer.scale_factor as f32;
+        let padding = PADDING * scale_factor;
+        let accent_width = ACCENT_WIDTH * scale_factor;
+        let scrollbar_width = SCROLLBAR_WIDTH * scale_factor;
+        let canvas_size = canvas.base_layer_size();
+        let (canvas_width, canvas_height) = (canvas_size.width as f32, canvas_size.height as f32);
+        let (foreground, background) = default_colors(grid_renderer);
+
+        self.history_rows =
+            ((canvas_height * HISTORY_SIZE_RATIO - padding * 2.0) / font_height).max(1.0) as usize;
+        let history = match &self.history {
+            Some(history) => history,
+            None => return,
+        };
+
+        let rows = history.lines.len().min(self.history_rows);
+        let width = canvas_width * HISTORY_SIZE_RATIO;
+        let height = rows as f32 * font_height + padding * 2.0;
+        let region = Rect::from_xywh(
+            (canvas_width - width) / 2.0,
+            (canvas_height - height) / 2.0,
+            width,
+            height,
+        );
+
+        canvas.save();
+        let rounded_region = draw_panel(canvas, region, scale_factor, foreground, background);
+        canvas.clip_rrect(rounded_region, None, Some(true));
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        let visible_lines = history.lines.iter().skip(history.scroll_offset).take(rows);
+        for (row, (kind, line)) in visible_lines.enumerate() {
+            let y = region.top + padding + row as f32 * font_height;
+            if let Some(color) = kind_color(kind) {
+                paint.set_color(color);
+                canvas.draw_rect(
+                    Rect::from_xywh(region.left, y, accent_width, font_height),
+                    &paint,
+                );
+            }
+            grid_renderer.draw_styled_text(canvas, line, (region.left + accent_width + padding, y));
+        }
+
+        if history.lines.len() > rows {
+            let track_height = height - padding * 2.0;
+            let thumb_height =
+                (track_height * rows as f32 / history.lines.len() as f32).max(font_height / 2.0);
+            let thumb_top = region.top
+                + padding
+                + (track_height - thumb_height) * history.scroll_offset as f32
+                    / (history.lines.len() - rows) as f32;
+            let thumb = Rect::from_xywh(
+                region.right - padding - scrollbar_width,
+                thumb_top,
+                scrollbar_width,
+                thumb_height,
+            );
+
+            paint.set_color(lerp_color(background, foreground, 0.4).to_color());
+            canvas.draw_rrect(
+                RRect::new_rect_xy(thumb, scrollbar_width / 2.0, scrollbar_width / 2.0),
+                &paint,
+            );
+        }
+
+        canvas.restore();
+    }
+}
+
+fn default_colors(grid_renderer: &GridRenderer) -> (Color4f, Color4f) {
+    let colors = &grid_renderer.default_style.colors;
+    (colors.foreground.unwrap(), colors.background.unwrap())
+}
+
+// Fills the rounded background shared by the toasts, the status and the history panel
+fn draw_panel(
+    canvas: &mut Canvas,
+    region: Rect,
+    scale_factor: f32,
+    foreground: Color4f,
+    background: Color4f,
+) -> RRect {
+    let rounded_region = RRect::new_rect_xy(
+        region,
+        CORNER_RADIUS * scale_factor,
+        CORNER_RADIUS * scale_factor,
+    );
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(lerp_color(background, foreground, 0.08).to_color());
+    canvas.draw_rrect(rounded_region, &paint);
+    rounded_region
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(chunks: &[&str]) -> StyledText {
+        chunks
+            .iter()
+            .map(|chunk| (None, chunk.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_lines() {
+        assert_eq!(
+            split_lines(text(&["E492: Not an", " editor command\nline two", "\n"])),
+            vec![
+                text(&["E492: Not an", " editor command"]),
+                text(&["line two"])
+            ]
+        );
+        assert_eq!(split_lines(text(&[])), vec![text(&[])]);
+    }
+
+    #[test]
+    fn test_toast_expiry() {
+        let mut toast = Toast::new(MessageKind::Echo, text(&["hello"]));
+        let now = toast.shown_at;
+        assert!(!toast.is_expired(now));
+        assert_eq!(toast.opacity(now), 1.0);
+        assert!(toast.is_expired(now + TOAST_TIMEOUT));
+        assert_eq!(toast.opacity(now + TOAST_TIMEOUT), 0.0);
+
+        toast.kind = MessageKind::ReturnPrompt;
+        assert!(!toast.is_expired(now + TOAST_TIMEOUT));
+    }
+
+    #[test]
+    fn test_clear_keeps_expiring_toasts() {
+        let mut messages = Messages::default();
+        messages.show(MessageKind::Echo, text(&["written"]), false);
+        messages.show(MessageKind::Confirm, text(&["Save changes?"]), false);
+        messages.show_history(vec![(MessageKind::Error, text(&["E37"]))]);
+
+        messages.clear();
+        assert_eq!(messages.toasts.len(), 1);
+        assert!(!messages.is_history_visible());
+    }
+}
@@ -1,9 +1,13 @@
 pub mod animation_utils;
+mod command_line;
 pub mod cursor_renderer;
 pub mod fonts;
 pub mod grid_renderer;
+mod messages;
+mod popup_menu;
 pub mod profiler;
 mod rendered_window;
+mod tabline;
 
 use std::{
     cmp::Ordering,
@@ -11,14 +15,14 @@ use std::{
     sync::Arc,
 };
 
-use glutin::event::Event;
+use glutin::event::{Event, MouseScrollDelta, WindowEvent};
 use log::error;
-use skia_safe::Canvas;
+use skia_safe::{colors, Canvas, Color, Paint, Point, Rect};
 use tokio::sync::mpsc::UnboundedReceiver;
 
 use crate::{
-    bridge::EditorMode,
-    editor::{Cursor, Style},
+    bridge::{EditorMode, MessageKind, PopupMenuItem, TabInfo},
+    editor::{CommandLine, Cursor, Style, StyledText},
     event_aggregator::EVENT_AGGREGATOR,
     settings::*,
     WindowSettings,
@@ -27,18 +31,29 @@ use crate::{
 use cursor_renderer::CursorRenderer;
 pub use fonts::caching_shaper::CachingShaper;
 pub use grid_renderer::GridRenderer;
-pub use rendered_window::{LineFragment, RenderedWindow, WindowDrawCommand, WindowDrawDetails};
+use messages::Messages;
+use popup_menu::PopupMenu;
+pub use popup_menu::{PopupMenuAnchor, PopupMenuStyles};
+pub use rendered_window::{
+    LineFragment, Link, RenderedWindow, WindowDrawCommand, WindowDrawDetails,
+};
+use tabline::Tabline;
 
 #[derive(SettingGroup, Clone)]
 pub struct RendererSettings {
     position_animation_length: f32,
     scroll_animation_length: f32,
+    #[setting(min = 0.0, max = 1.0, clamp)]
     floating_opacity: f32,
     floating_blur: bool,
     floating_blur_amount_x: f32,
     floating_blur_amount_y: f32,
     debug_renderer: bool,
     profiler: bool,
+    pub line_space: f32,
+    pub ext_cmdline: bool,
+    pub ext_messages: bool,
+    pub ext_tabline: bool,
 }
 
 impl Default for RendererSettings {
@@ -52,6 +67,10 @@ impl Default for RendererSettings {
             floating_blur_amount_y: 2.0,
             debug_renderer: false,
             profiler: false,
+            line_space: 0.0,
+            ext_cmdline: false,
+            ext_messages: false,
+            ext_tabline: false,
         }
     }
 }
@@ -65,8 +84,36 @@ pub enum DrawCommand {
     },
     UpdateCursor(Cursor),
     FontChanged(String),
+    FontWideChanged(String),
+    LineSpaceChanged(i64),
+    PumblendChanged(u64),
     DefaultStyleChanged(Style),
     ModeChanged(EditorMode),
+    ConnectionStatus(Option<String>),
+    CommandLine(CommandLine),
+    PopupMenuShow {
+        items: Vec<PopupMenuItem>,
+        selected: Option<u64>,
+        anchor: PopupMenuAnchor,
+        styles: PopupMenuStyles,
+    },
+    PopupMenuSelect(Option<u64>),
+    PopupMenuHide,
+    ShowMessage {
+        kind: MessageKind,
+        content: StyledText,
+        replace_last: bool,
+    },
+    ClearMessages,
+    ShowMode(StyledText),
+    ShowCommand(StyledText),
+    Ruler(StyledText),
+    ShowMessageHistory(Vec<(MessageKind, StyledText)>),
+    Tabline {
+        current_tab: u64,
+        tabs: Vec<TabInfo>,
+    },
+    EndOfReplay,
 }
 
 pub struct Renderer {
@@ -79,10 +126,30 @@ pub struct Renderer {
 
     pub batched_draw_command_receiver: UnboundedReceiver<Vec<DrawCommand>>,
     profiler: profiler::Profiler,
+    connection_status: Option<String>,
+    command_line: CommandLine,
+    popup_menu: Option<PopupMenu>,
+    messages: Messages,
+    tabline: Tabline,
+    // The 'linespace' option and the line_space setting, added together for the grid
+    linespace_option: i64,
+    line_space: f32,
+    pumblend: u64,
+    pointer: Point,
 }
 
 impl Renderer {
     pub fn new(scale_factor: f64) -> Self {
+        Self::with_draw_command_receiver(
+            scale_factor,
+            EVENT_AGGREGATOR.register_event::<Vec<DrawCommand>>(),
+        )
+    }
+
+    pub fn with_draw_command_receiver(
+        scale_factor: f64,
+        batched_draw_command_receiver: UnboundedReceiver<Vec<DrawCommand>>,
+    ) -> Self {
         let cursor_renderer = CursorRenderer::new();
         let grid_renderer = GridRenderer::new(scale_factor);
         let current_mode = EditorMode::Unknown(String::from(""));
@@ -90,7 +157,6 @@ impl Renderer {
         let rendered_windows = HashMap::new();
         let window_regions = Vec::new();
 
-        let batched_draw_command_receiver = EVENT_AGGREGATOR.register_event::<Vec<DrawCommand>>();
         let profiler = profiler::Profiler::new(12.0);
 
         Renderer {
@@ -101,11 +167,130 @@ impl Renderer {
             window_regions,
             batched_draw_command_receiver,
             profiler,
+            connection_status: None,
+            command_line: CommandLine::default(),
+            popup_menu: None,
+            messages: Messages::default(),
+            tabline: Tabline::default(),
+            linespace_option: 0,
+            line_space: 0.0,
+            pumblend: 0,
+            pointer: Point::default(),
+        }
+    }
+
+    /// Applies a new value of the line_space setting
+    ///
+    /// # Returns
+    /// `bool` indicating whether or not the font dimensions changed.
+    pub fn update_line_space(&mut self, line_space: f32) -> bool {
+        if (self.line_space - line_space).abs() <= f32::EPSILON {
+            return false;
         }
+        self.line_space = line_space;
+        self.update_linespace();
+        true
+    }
+
+    // The line_space setting allows negative and fractional values, which 'linespace' can't
+    fn update_linespace(&mut self) {
+        self.grid_renderer
+            .update_linespace(self.linespace_option as f32 + self.line_space);
     }
 
     pub fn handle_event(&mut self, event: &Event<()>) {
         self.cursor_renderer.handle_event(event);
+        self.tabline.handle_event(event);
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as i64,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.y / self.grid_renderer.font_dimensions.height as f64) as i64
+                    }
+                };
+                self.messages.scroll_history(lines);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                self.pointer = Point::new(position.x as f32, position.y as f32);
+            }
+            _ => {}
+        }
+    }
+
+    // The link under the pointer in the topmost window containing it, along with that window's
+    // region in window coordinates
+    fn link_under_pointer(&self) -> Option<(Rect, &Link)> {
+        let font_dimensions = self.grid_renderer.font_dimensions;
+        let details = self.window_regions.iter().rev().find(|details| {
+            let region = details.region;
+            self.pointer.x >= region.left
+                && self.pointer.x < region.right
+                && self.pointer.y >= region.top
+                && self.pointer.y < region.bottom
+        })?;
+
+        let row = ((self.pointer.y - details.region.top) / font_dimensions.height as f32) as u64;
+        let column = ((self.pointer.x - details.region.left) / font_dimensions.width as f32) as u64;
+        self.rendered_windows
+            .get(&details.id)?
+            .links
+            .iter()
+            .find(|link| link.row == row && link.start <= column && column < link.end)
+            .map(|link| (details.region, link))
+    }
+
+    // Url of the highlight under the pointer, which ctrl+click opens
+    pub fn hovered_link(&self) -> Option<String> {
+        self.link_under_pointer().map(|(_, link)| link.url.clone())
+    }
+
+    fn draw_hovered_link(&mut self, canvas: &mut Canvas, tabline_height: f32) {
+        let (region, link) = match self.link_under_pointer() {
+            Some((region, link)) => (region, link.clone()),
+            None => return,
+        };
+
+        let font_dimensions = self.grid_renderer.font_dimensions;
+        let (position, stroke_size) = self.grid_renderer.shaper.underline_metrics();
+        let y = region.top - tabline_height + (link.row * font_dimensions.height) as f32 + position;
+        let left = region.left + (link.start * font_dimensions.width) as f32;
+        let right = region.left + (link.end * font_dimensions.width) as f32;
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(false);
+        paint.set_stroke_width(stroke_size);
+        paint.set_color(
+            self.grid_renderer
+                .default_style
+                .colors
+                .foreground
+                .unwrap()
+                .to_color(),
+        );
+        canvas.draw_line((left, y), (right, y), &paint);
+    }
+
+    // The message history panel takes the mouse wheel while it is shown
+    pub fn is_message_history_visible(&self) -> bool {
+        self.messages.is_history_visible()
+    }
+
+    // Height in pixels taken by the tabline above the grid
+    pub fn tabline_height(&self) -> f32 {
+        self.tabline.height(&self.grid_renderer)
+    }
+
+    // Pointer events over the tabline are handled by it instead of being sent to neovim
+    pub fn is_over_tabline(&self, y: f32) -> bool {
+        self.tabline.contains(y)
     }
 
     pub fn font_names(&self) -> Vec<String> {
@@ -116,17 +301,30 @@ impl Renderer {
     ///
     /// # Returns
     /// `bool` indicating whether or not font was changed during this frame.
-    #[allow(clippy::needless_collect)]
     pub fn draw_frame(&mut self, root_canvas: &mut Canvas, dt: f32) -> bool {
         let mut draw_commands = Vec::new();
         while let Ok(draw_command) = self.batched_draw_command_receiver.try_recv() {
             draw_commands.extend(draw_command);
         }
 
+        self.draw_batch(root_canvas, draw_commands, dt)
+    }
+
+    /// Draws frame after applying an already received batch of draw commands
+    ///
+    /// # Returns
+    /// `bool` indicating whether or not font was changed during this frame.
+    #[allow(clippy::needless_collect)]
+    pub fn draw_batch(
+        &mut self,
+        root_canvas: &mut Canvas,
+        draw_commands: Vec<DrawCommand>,
+        dt: f32,
+    ) -> bool {
         let mut font_changed = false;
 
         for draw_command in draw_commands.into_iter() {
-            if let DrawCommand::FontChanged(_) = draw_command {
+            if let DrawCommand::FontChanged(_) | DrawCommand::LineSpaceChanged(_) = draw_command {
                 font_changed = true;
             }
             self.handle_draw_command(root_canvas, draw_command);
@@ -140,6 +338,11 @@ impl Renderer {
         root_canvas.save();
         root_canvas.reset_matrix();
 
+        self.tabline.draw(root_canvas, &mut self.grid_renderer);
+        let tabline_height = self.tabline_height();
+        root_canvas.save();
+        root_canvas.translate((0.0, tabline_height));
+
         if let Some(root_window) = self.rendered_windows.get(&1) {
             let clip_rect = root_window.pixel_region(font_dimensions);
             root_canvas.clip_rect(&clip_rect, None, Some(false));
@@ -178,7 +381,12 @@ impl Renderer {
                     dt,
                 )
             })
+            .map(|details| WindowDrawDetails {
+                region: details.region.with_offset((0.0, tabline_height)),
+                ..details
+            })
             .collect();
+        self.draw_hovered_link(root_canvas, tabline_height);
 
         let windows = &self.rendered_windows;
         self.cursor_renderer
@@ -186,14 +394,63 @@ impl Renderer {
 
         self.cursor_renderer
             .draw(&mut self.grid_renderer, &self.current_mode, root_canvas, dt);
+        root_canvas.restore();
+
+        let command_line_origin = command_line::draw_command_line(
+            root_canvas,
+            &self.command_line,
+            &mut self.grid_renderer,
+        );
+        if let Some(popup_menu) = &self.popup_menu {
+            popup_menu.draw(
+                root_canvas,
+                &mut self.grid_renderer,
+                &self.command_line,
+                command_line_origin,
+                tabline_height,
+                self.pumblend,
+            );
+        }
+        self.messages.draw(root_canvas, &mut self.grid_renderer);
 
         self.profiler.draw(root_canvas, dt);
 
+        if let Some(connection_status) = self.connection_status.clone() {
+            self.draw_connection_status(root_canvas, connection_status);
+        }
+
         root_canvas.restore();
 
         font_changed
     }
 
+    /// Dims the last rendered frame and draws the connection status centered on top of it
+    fn draw_connection_status(&mut self, root_canvas: &mut Canvas, status: String) {
+        let size = root_canvas.base_layer_size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        let font_dimensions = self.grid_renderer.font_dimensions;
+
+        let mut paint = Paint::default();
+        paint.set_color(Color::from_argb(180, 0, 0, 0));
+        root_canvas.draw_rect(Rect::from_wh(width, height), &paint);
+
+        let text_width = (status.chars().count() as u64 * font_dimensions.width) as f32;
+        let x = ((width - text_width) / 2.0).max(0.0);
+        let y = ((height - font_dimensions.height as f32) / 2.0).max(0.0);
+        let y_adjustment = self.grid_renderer.shaper.y_adjustment() as f32;
+
+        paint.set_color(colors::WHITE);
+        paint.set_anti_alias(true);
+        for blob in self
+            .grid_renderer
+            .shaper
+            .shape_cached(status, false, false)
+            .iter()
+        {
+            root_canvas.draw_text_blob(blob, (x, y + y_adjustment), &paint);
+        }
+    }
+
     fn handle_draw_command(&mut self, root_canvas: &mut Canvas, draw_command: DrawCommand) {
         match draw_command {
             DrawCommand::Window {
@@ -236,12 +493,55 @@ impl Renderer {
             DrawCommand::FontChanged(new_font) => {
                 self.grid_renderer.update_font(&new_font);
             }
+            DrawCommand::FontWideChanged(new_font_wide) => {
+                self.grid_renderer.update_font_wide(&new_font_wide);
+            }
+            DrawCommand::PumblendChanged(pumblend) => {
+                self.pumblend = pumblend;
+            }
+            DrawCommand::LineSpaceChanged(linespace) => {
+                self.linespace_option = linespace;
+                self.update_linespace();
+            }
             DrawCommand::DefaultStyleChanged(new_style) => {
                 self.grid_renderer.default_style = Arc::new(new_style);
             }
             DrawCommand::ModeChanged(new_mode) => {
                 self.current_mode = new_mode;
             }
+            DrawCommand::ConnectionStatus(connection_status) => {
+                self.connection_status = connection_status;
+            }
+            DrawCommand::CommandLine(command_line) => {
+                self.command_line = command_line;
+            }
+            DrawCommand::PopupMenuShow {
+                items,
+                selected,
+                anchor,
+                styles,
+            } => {
+                self.popup_menu = Some(PopupMenu::new(items, selected, anchor, styles));
+            }
+            DrawCommand::PopupMenuSelect(selected) => {
+                if let Some(popup_menu) = &mut self.popup_menu {
+                    popup_menu.select(selected);
+                }
+            }
+            DrawCommand::PopupMenuHide => {
+                self.popup_menu = None;
+            }
+            DrawCommand::ShowMessage {
+                kind,
+                content,
+                replace_last,
+            } => self.messages.show(kind, content, replace_last),
+            DrawCommand::ClearMessages => self.messages.clear(),
+            DrawCommand::ShowMode(content) => self.messages.mode = content,
+            DrawCommand::ShowCommand(content) => self.messages.command = content,
+            DrawCommand::Ruler(content) => self.messages.ruler = content,
+            DrawCommand::ShowMessageHistory(entries) => self.messages.show_history(entries),
+            DrawCommand::Tabline { current_tab, tabs } => self.tabline.update(current_tab, tabs),
             _ => {}
         }
     }
@@ -273,3 +573,46 @@ fn floating_sort(window_a: &&mut RenderedWindow, window_b: &&mut RenderedWindow)
     }
     ord
 }
+
+#[cfg(test)]
+mod tests {
+    use skia_safe::Surface;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::renderer::cursor_renderer::CursorSettings;
+
+    #[test]
+    fn test_line_space_changes_font_dimensions() {
+        WindowSettings::register();
+        RendererSettings::register();
+        CursorSettings::register();
+
+        let mut renderer = Renderer::with_draw_command_receiver(1.0, unbounded_channel().1);
+        let base_dimensions = renderer.grid_renderer.font_dimensions;
+
+        assert!(renderer.update_line_space(3.0));
+        assert!(!renderer.update_line_space(3.0));
+        assert_eq!(
+            renderer.grid_renderer.font_dimensions.height,
+            base_dimensions.height + 3
+        );
+        assert_eq!(
+            renderer.grid_renderer.font_dimensions.width,
+            base_dimensions.width
+        );
+
+        // The 'linespace' option adds to the setting, and resizes the grid like a font change
+        let mut surface = Surface::new_raster_n32_premul((100, 100)).unwrap();
+        let font_changed = renderer.draw_batch(
+            surface.canvas(),
+            vec![DrawCommand::LineSpaceChanged(2)],
+            0.0,
+        );
+        assert!(font_changed);
+        assert_eq!(
+            renderer.grid_renderer.font_dimensions.height,
+            base_dimensions.height + 5
+        );
+    }
+}
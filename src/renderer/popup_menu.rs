@@ -0,0 +1,477 @@
+use std::sync::Arc;
+
+use skia_safe::{Canvas, Color4f, Paint, Point, RRect, Rect};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    bridge::PopupMenuItem,
+    editor::{blend_to_alpha, Colors, CommandLine, Style},
+    renderer::{animation_utils::lerp_color, GridRenderer},
+};
+
+const MAX_VISIBLE_ITEMS: usize = 15;
+// Sizes in logical pixels, scaled by the window scale factor when drawn
+const PADDING: f32 = 4.0;
+const CORNER_RADIUS: f32 = 6.0;
+const SCROLLBAR_WIDTH: f32 = 4.0;
+
+// The highlight groups neovim draws its own popup menu with, so the colorscheme applies. Groups
+// the colorscheme or neovim version doesn't define are derived from the default colors.
+#[derive(Clone, Debug, Default)]
+pub struct PopupMenuStyles {
+    pub normal: Option<Arc<Style>>,
+    pub selected: Option<Arc<Style>>,
+    pub kind: Option<Arc<Style>>,
+    pub kind_selected: Option<Arc<Style>>,
+    pub extra: Option<Arc<Style>>,
+    pub thumb: Option<Arc<Style>>,
+}
+
+struct PopupMenuColors {
+    background: Color4f,
+    foreground: Color4f,
+    selected_background: Color4f,
+    selected_foreground: Color4f,
+    kind: Color4f,
+    kind_selected: Color4f,
+    extra: Color4f,
+    thumb: Color4f,
+}
+
+impl PopupMenuStyles {
+    fn colors(&self, default_colors: &Colors) -> PopupMenuColors {
+        let default_background = default_colors.background.unwrap();
+        let default_foreground = default_colors.foreground.unwrap();
+        let background = |style: &Option<Arc<Style>>, fallback| {
+            style
+                .as_ref()
+                .map_or(fallback, |style| style.background(default_colors))
+        };
+        let foreground = |style: &Option<Arc<Style>>, fallback| {
+            style
+                .as_ref()
+                .map_or(fallback, |style| style.foreground(default_colors))
+        };
+
+        let normal_background = background(
+            &self.normal,
+            lerp_color(default_background, default_foreground, 0.08),
+        );
+        let normal_foreground = foreground(&self.normal, default_foreground);
+        let selected_foreground = foreground(&self.selected, normal_foreground);
+        PopupMenuColors {
+            background: normal_background,
+            foreground: normal_foreground,
+            selected_background: background(
+                &self.selected,
+                lerp_color(default_background, default_foreground, 0.25),
+            ),
+            selected_foreground,
+            kind: foreground(&self.kind, normal_foreground),
+            kind_selected: foreground(&self.kind_selected, selected_foreground),
+            extra: foreground(
+                &self.extra,
+                lerp_color(normal_background, normal_foreground, 0.6),
+            ),
+            thumb: background(
+                &self.thumb,
+                lerp_color(normal_background, normal_foreground, 0.4),
+            ),
+        }
+    }
+}
+
+// Completion kinds grouped by what they name. Vim's own completion uses single letters, while
+// completion plugins usually pass LSP kind names, often after an icon.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KindCategory {
+    Function,
+    Variable,
+    Type,
+    Keyword,
+    Other,
+}
+
+fn kind_category(kind: &str) -> KindCategory {
+    let name = kind.split_whitespace().last().unwrap_or("").to_lowercase();
+    match name.as_str() {
+        "f" | "function" | "method" | "constructor" => KindCategory::Function,
+        "v" | "m" | "variable" | "field" | "property" | "constant" | "value" | "enummember" => {
+            KindCategory::Variable
+        }
+        "t" | "class" | "struct" | "interface" | "enum" | "module" | "typeparameter" => {
+            KindCategory::Type
+        }
+        "d" | "keyword" | "snippet" | "operator" => KindCategory::Keyword,
+        _ => KindCategory::Other,
+    }
+}
+
+impl PopupMenuColors {
+    // The PmenuKind colors tinted towards a hue per kind category, so they still follow the
+    // colorscheme. Kinds which aren't recognized keep the plain PmenuKind colors.
+    fn kind_color(&self, kind: &str, is_selected: bool) -> Color4f {
+        let base = if is_selected {
+            self.kind_selected
+        } else {
+            self.kind
+        };
+        let tint = match kind_category(kind) {
+            KindCategory::Function => Color4f::new(0.35, 0.6, 1.0, base.a),
+            KindCategory::Variable => Color4f::new(1.0, 0.6, 0.25, base.a),
+            KindCategory::Type => Color4f::new(0.4, 0.8, 0.4, base.a),
+            KindCategory::Keyword => Color4f::new(0.75, 0.45, 0.95, base.a),
+            KindCategory::Other => return base,
+        };
+        lerp_color(base, tint, 0.5)
+    }
+}
+
+// Width in cells, with wide characters taking up two
+fn text_width(text: &str) -> usize {
+    text.width()
+}
+
+#[derive(Clone, Debug)]
+pub enum PopupMenuAnchor {
+    // Position in grid cells of the cell the completed word starts at
+    Grid { left: f64, top: f64 },
+    // Byte position in the text of the command line drawn by the renderer
+    CommandLine { position: u64 },
+}
+
+pub struct PopupMenu {
+    items: Vec<PopupMenuItem>,
+    selected: Option<u64>,
+    anchor: PopupMenuAnchor,
+    styles: PopupMenuStyles,
+    scroll_offset: usize,
+}
+
+impl PopupMenu {
+    pub fn new(
+        items: Vec<PopupMenuItem>,
+        selected: Option<u64>,
+        anchor: PopupMenuAnchor,
+        styles: PopupMenuStyles,
+    ) -> PopupMenu {
+        let mut popup_menu = PopupMenu {
+            items,
+            selected: None,
+            anchor,
+            styles,
+            scroll_offset: 0,
+        };
+        popup_menu.select(selected);
+        popup_menu
+    }
+
+    pub fn select(&mut self, selected: Option<u64>) {
+        self.selected = selected;
+
+        // Keep the selected item in view
+        if let Some(selected) = selected.map(|selected| selected as usize) {
+            if selected < self.scroll_offset {
+                self.scroll_offset = selected;
+            } else if selected >= self.scroll_offset + MAX_VISIBLE_ITEMS {
+                self.scroll_offset = selected + 1 - MAX_VISIBLE_ITEMS;
+            }
+        }
+    }
+
+    fn visible_count(&self) -> usize {
+        self.items.len().min(MAX_VISIBLE_ITEMS)
+    }
+
+    // Widths in cells of the word, kind and menu columns
+    fn column_widths(&self) -> (usize, usize, usize) {
+        self.items
+            .iter()
+            .fold((0, 0, 0), |(word, kind, menu), item| {
+                (
+                    word.max(text_width(&item.word)),
+                    kind.max(text_width(&item.kind)),
+                    menu.max(text_width(&item.menu)),
+                )
+            })
+    }
+
+    // Pixel position of the top left corner of the anchor cell, where the grid starts grid_top
+    // pixels down the window
+    fn anchor_point(
+        &self,
+        grid_renderer: &GridRenderer,
+        command_line: &CommandLine,
+        command_line_origin: Option<Point>,
+        grid_top: f32,
+    ) -> Point {
+        let font_width = grid_renderer.font_dimensions.width as f32;
+        let font_height = grid_renderer.font_dimensions.height as f32;
+
+        match self.anchor {
+            PopupMenuAnchor::Grid { left, top } => Point::new(
+                left as f32 * font_width,
+                top as f32 * font_height + grid_top,
+            ),
+            PopupMenuAnchor::CommandLine { position } => {
+                let column = command_line
+                    .levels
+                    .last()
+                    .map_or(0, |level| level.column_at(position));
+                command_line_origin.unwrap_or_default()
+                    + Point::new(column as f32 * font_width, 0.0)
+            }
+        }
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas,
+        grid_renderer: &mut GridRenderer,
+        command_line: &CommandLine,
+        command_line_origin: Option<Point>,
+        grid_top: f32,
+        pumblend: u64,
+    ) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        let font_width = grid_renderer.font_dimensions.width as f32;
+        let font_height = grid_renderer.font_dimensions.height as f32;
+        let scale_factor = grid_renderer.scale_factor as f32;
+        let padding = PADDING * scale_factor;
+        let scrollbar_width = SCROLLBAR_WIDTH * scale_factor;
+        let has_scrollbar = self.items.len() > MAX_VISIBLE_ITEMS;
+
+        let (word_width, kind_width, menu_width) = self.column_widths();
+        let kind_column = word_width + 1;
+        let menu_column = kind_column + if kind_width > 0 { kind_width + 1 } else { 0 };
+        let text_columns = menu_column + menu_width;
+
+        let mut width = text_columns as f32 * font_width + padding * 2.0;
+        if has_scrollbar {
+            width += scrollbar_width + padding;
+        }
+        let height = self.visible_count() as f32 * font_height + padding * 2.0;
+
+        // Open below the anchor cell when there is room, otherwise above it
+        let canvas_size = canvas.base_layer_size();
+        let anchor = self.anchor_point(grid_renderer, command_line, command_line_origin, grid_top);
+        let below = anchor.y + font_height;
+        let top = if below + height <= canvas_size.height as f32 || anchor.y < height {
+            below
+        } else {
+            anchor.y - height
+        };
+        let left = (anchor.x - padding)
+            .min(canvas_size.width as f32 - width)
+            .max(0.0);
+        let region = Rect::from_xywh(left, top, width, height);
+
+        let colors = self.styles.colors(&grid_renderer.default_style.colors);
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+
+        canvas.save();
+        let rounded_region = RRect::new_rect_xy(
+            region,
+            CORNER_RADIUS * scale_factor,
+            CORNER_RADIUS * scale_factor,
+        );
+        canvas.clip_rrect(rounded_region, None, Some(true));
+
+        // 'pumblend' makes the background translucent while the text stays opaque
+        let background_alpha = blend_to_alpha(pumblend);
+        paint.set_color(colors.background.to_color().with_a(background_alpha));
+        canvas.draw_rrect(rounded_region, &paint);
+
+        let text_left = left + padding;
+        let text_top = top + padding;
+        let y_adjustment = grid_renderer.shaper.y_adjustment() as f32;
+        let visible_items = self
+            .items
+            .iter()
+            .enumerate()
+            .skip(self.scroll_offset)
+            .take(MAX_VISIBLE_ITEMS);
+
+        for (row, (index, item)) in visible_items.enumerate() {
+            let y = text_top + row as f32 * font_height;
+
+            let is_selected = self.selected == Some(index as u64);
+            if is_selected {
+                paint.set_color(
+                    colors
+                        .selected_background
+                        .to_color()
+                        .with_a(background_alpha),
+                );
+                canvas.draw_rect(Rect::from_xywh(left, y, width, font_height), &paint);
+            }
+
+            let word_color = if is_selected {
+                colors.selected_foreground
+            } else {
+                colors.foreground
+            };
+            let kind_color = colors.kind_color(&item.kind, is_selected);
+            let columns = [
+                (&item.word, 0, word_color.to_color()),
+                (&item.kind, kind_column, kind_color.to_color()),
+                (&item.menu, menu_column, colors.extra.to_color()),
+            ];
+            for (text, column, color) in columns {
+                if text.is_empty() {
+                    continue;
+                }
+
+                paint.set_color(color);
+                let x = text_left + column as f32 * font_width;
+                for blob in grid_renderer
+                    .shaper
+                    .shape_cached(text.clone(), false, false)
+                    .iter()
+                {
+                    canvas.draw_text_blob(blob, (x, y + y_adjustment), &paint);
+                }
+            }
+        }
+
+        if has_scrollbar {
+            let track_height = height - padding * 2.0;
+            let thumb_height = (track_height * MAX_VISIBLE_ITEMS as f32 / self.items.len() as f32)
+                .max(font_height / 2.0);
+            let thumb_top = text_top
+                + (track_height - thumb_height) * self.scroll_offset as f32
+                    / (self.items.len() - MAX_VISIBLE_ITEMS) as f32;
+            let thumb = Rect::from_xywh(
+                left + width - padding - scrollbar_width,
+                thumb_top,
+                scrollbar_width,
+                thumb_height,
+            );
+
+            paint.set_color(colors.thumb.to_color());
+            canvas.draw_rrect(
+                RRect::new_rect_xy(thumb, scrollbar_width / 2.0, scrollbar_width / 2.0),
+                &paint,
+            );
+        }
+
+        canvas.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(count: usize) -> Vec<PopupMenuItem> {
+        (0..count)
+            .map(|index| PopupMenuItem {
+                word: format!("item{}", index),
+                kind: "f".to_owned(),
+                menu: String::new(),
+                info: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_scrolls_into_view() {
+        let mut popup_menu = PopupMenu::new(
+            items(40),
+            Some(20),
+            PopupMenuAnchor::Grid {
+                left: 0.0,
+                top: 0.0,
+            },
+            PopupMenuStyles::default(),
+        );
+        assert_eq!(popup_menu.scroll_offset, 20 + 1 - MAX_VISIBLE_ITEMS);
+
+        popup_menu.select(Some(10));
+        assert_eq!(popup_menu.scroll_offset, 10);
+
+        popup_menu.select(None);
+        assert_eq!(popup_menu.scroll_offset, 10);
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let mut items = items(2);
+        items[1].word = "longer_word".to_owned();
+        items[1].menu = "[LSP]".to_owned();
+        let popup_menu = PopupMenu::new(
+            items,
+            None,
+            PopupMenuAnchor::Grid {
+                left: 0.0,
+                top: 0.0,
+            },
+            PopupMenuStyles::default(),
+        );
+
+        assert_eq!(popup_menu.column_widths(), (11, 1, 5));
+    }
+
+    #[test]
+    fn test_wide_column_widths() {
+        let mut items = items(1);
+        items[0].word = "日本語".to_owned();
+        let popup_menu = PopupMenu::new(
+            items,
+            None,
+            PopupMenuAnchor::Grid {
+                left: 0.0,
+                top: 0.0,
+            },
+            PopupMenuStyles::default(),
+        );
+
+        assert_eq!(popup_menu.column_widths(), (6, 1, 0));
+    }
+
+    #[test]
+    fn test_colors_follow_highlight_groups() {
+        let default_colors = Colors::new(
+            Some(Color4f::new(1.0, 1.0, 1.0, 1.0)),
+            Some(Color4f::new(0.0, 0.0, 0.0, 1.0)),
+            None,
+        );
+        let red = Color4f::new(1.0, 0.0, 0.0, 1.0);
+        let styles = PopupMenuStyles {
+            kind: Some(Arc::new(Style::new(Colors::new(Some(red), None, None)))),
+            ..Default::default()
+        };
+
+        let colors = styles.colors(&default_colors);
+        assert_eq!(colors.kind, red);
+        // Groups which aren't defined fall back to the normal popup colors
+        assert_eq!(colors.kind_selected, colors.foreground);
+    }
+    #[test]
+    fn test_kind_colors() {
+        assert_eq!(kind_category("f"), KindCategory::Function);
+        assert_eq!(kind_category("Method"), KindCategory::Function);
+        assert_eq!(kind_category("ƒ Variable"), KindCategory::Variable);
+        assert_eq!(kind_category("Struct"), KindCategory::Type);
+        assert_eq!(kind_category("Keyword"), KindCategory::Keyword);
+        assert_eq!(kind_category("Text"), KindCategory::Other);
+
+        let default_colors = Colors::new(
+            Some(Color4f::new(1.0, 1.0, 1.0, 1.0)),
+            Some(Color4f::new(0.0, 0.0, 0.0, 1.0)),
+            None,
+        );
+        let colors = PopupMenuStyles::default().colors(&default_colors);
+        let function = colors.kind_color("Function", false);
+        assert_eq!(function, colors.kind_color("f", false));
+        assert_ne!(function, colors.kind_color("Variable", false));
+        // Unknown kinds fall back to PmenuKind
+        assert_eq!(colors.kind_color("Text", false), colors.kind);
+        assert_eq!(colors.kind_color("", true), colors.kind_selected);
+    }
+}
@@ -24,6 +24,15 @@ pub struct LineFragment {
     pub style: Option<Arc<Style>>,
 }
 
+// Cells on a row sharing the url of their highlight, up to but not including end
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub row: u64,
+    pub start: u64,
+    pub end: u64,
+    pub url: String,
+}
+
 #[derive(Clone, Debug)]
 pub enum WindowDrawCommand {
     Position {
@@ -48,6 +57,7 @@ pub enum WindowDrawCommand {
         top_line: f64,
         bottom_line: f64,
     },
+    Links(Vec<Link>),
 }
 
 fn build_window_surface(parent_canvas: &mut Canvas, pixel_size: (i32, i32)) -> Surface {
@@ -140,6 +150,8 @@ pub struct RenderedWindow {
     pub current_scroll: f32,
     scroll_destination: f32,
     scroll_t: f32,
+
+    pub links: Vec<Link>,
 }
 
 #[derive(Clone, Debug)]
@@ -177,6 +189,8 @@ impl RenderedWindow {
             current_scroll: 0.0,
             scroll_destination: 0.0,
             scroll_t: 2.0, // 2.0 is out of the 0.0 to 1.0 range and stops animation
+
+            links: Vec::new(),
         }
     }
 
@@ -479,6 +493,7 @@ impl RenderedWindow {
                 }
             }
             WindowDrawCommand::Hide => self.hidden = true,
+            WindowDrawCommand::Links(links) => self.links = links,
             WindowDrawCommand::Viewport { top_line, .. } => {
                 if self.current_surface.top_line != top_line as u64 {
                     let new_snapshot = self.current_surface.snapshot();
@@ -0,0 +1,331 @@
+use std::path::Path;
+
+use glutin::event::{ElementState, Event, MouseButton, WindowEvent};
+use skia_safe::{Canvas, Paint, Point, Rect};
+
+use crate::{
+    bridge::{ParallelCommand, TabInfo, UiCommand},
+    event_aggregator::EVENT_AGGREGATOR,
+    renderer::{animation_utils::lerp_color, GridRenderer},
+};
+
+// Tabs are at most this many cells wide, and shrink to share the window width when there are many
+const MAX_TAB_COLUMNS: usize = 30;
+// Sizes in logical pixels, scaled by the window scale factor when drawn
+const PADDING: f32 = 6.0;
+const ACCENT_HEIGHT: f32 = 2.0;
+const DRAG_THRESHOLD: f32 = 4.0;
+
+// Buffer names are usually full paths, which would make every tab as wide as allowed
+fn tab_title(name: &str) -> String {
+    if name.is_empty() {
+        return "[No Name]".to_owned();
+    }
+
+    Path::new(name)
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_owned())
+}
+
+fn contains(rect: &Rect, point: Point) -> bool {
+    point.x >= rect.left && point.x < rect.right && point.y >= rect.top && point.y < rect.bottom
+}
+
+// Index the dragged tab ends up at when its center is dropped at x, which is after every other tab
+// whose center it has passed
+fn drop_index(centers: &[f32], dragged: usize, x: f32) -> usize {
+    centers
+        .iter()
+        .enumerate()
+        .filter(|(index, center)| *index != dragged && **center < x)
+        .count()
+}
+
+// `:tabmove N` places the current tab after tab number N, so a tab dragged to the right has to name
+// the tab which ends up in front of it, one past its final index
+fn tabmove_argument(dragged: usize, index: usize) -> usize {
+    if index > dragged {
+        index + 1
+    } else {
+        index
+    }
+}
+
+struct TabDrag {
+    index: usize,
+    start_x: f32,
+    moved: bool,
+}
+
+struct TabRegion {
+    tab: Rect,
+    close_button: Rect,
+}
+
+#[derive(Default)]
+pub struct Tabline {
+    tabs: Vec<TabInfo>,
+    current_tab: u64,
+    regions: Vec<TabRegion>,
+    height: f32,
+    pointer: Point,
+    drag: Option<TabDrag>,
+}
+
+impl Tabline {
+    pub fn update(&mut self, current_tab: u64, tabs: Vec<TabInfo>) {
+        self.current_tab = current_tab;
+        if tabs.len() != self.tabs.len() {
+            self.drag = None;
+        }
+        self.tabs = tabs;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.tabs.is_empty()
+    }
+
+    // Height in pixels the editor grid is moved down by
+    pub fn height(&self, grid_renderer: &GridRenderer) -> f32 {
+        if !self.is_visible() {
+            return 0.0;
+        }
+
+        let padding = PADDING * grid_renderer.scale_factor as f32;
+        grid_renderer.font_dimensions.height as f32 + padding * 2.0
+    }
+
+    pub fn contains(&self, y: f32) -> bool {
+        self.is_visible() && y < self.height
+    }
+
+    fn tab_under_pointer(&self) -> Option<usize> {
+        self.regions
+            .iter()
+            .position(|region| contains(&region.tab, self.pointer))
+    }
+
+    pub fn handle_event(&mut self, event: &Event<()>) {
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                self.pointer = Point::new(position.x as f32, position.y as f32);
+                if let Some(drag) = &mut self.drag {
+                    drag.moved =
+                        drag.moved || (self.pointer.x - drag.start_x).abs() > DRAG_THRESHOLD;
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                if !self.contains(self.pointer.y) {
+                    return;
+                }
+
+                if let Some(index) = self.tab_under_pointer() {
+                    let tab = self.tabs[index].tab;
+                    if contains(&self.regions[index].close_button, self.pointer) {
+                        EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::CloseTab(tab)));
+                    } else {
+                        EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::SwitchTab(tab)));
+                        self.drag = Some(TabDrag {
+                            index,
+                            start_x: self.pointer.x,
+                            moved: false,
+                        });
+                    }
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(drag) = self.drag.take() {
+                    if !drag.moved || drag.index >= self.tabs.len() {
+                        return;
+                    }
+
+                    let centers: Vec<f32> = self
+                        .regions
+                        .iter()
+                        .map(|region| region.tab.center_x())
+                        .collect();
+                    let dragged_center = centers[drag.index] + self.pointer.x - drag.start_x;
+                    let index = drop_index(&centers, drag.index, dragged_center);
+                    if index != drag.index {
+                        EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::MoveTab {
+                            tab: self.tabs[drag.index].tab,
+                            after: tabmove_argument(drag.index, index) as u64,
+                        }));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas, grid_renderer: &mut GridRenderer) {
+        self.height = self.height(grid_renderer);
+        self.regions.clear();
+        if !self.is_visible() {
+            return;
+        }
+
+        let font_width = grid_renderer.font_dimensions.width as f32;
+        let font_height = grid_renderer.font_dimensions.height as f32;
+        let scale_factor = grid_renderer.scale_factor as f32;
+        let padding = PADDING * scale_factor;
+        let canvas_width = canvas.base_layer_size().width as f32;
+
+        let colors = &grid_renderer.default_style.colors;
+        let foreground = colors.foreground.unwrap();
+        let background = colors.background.unwrap();
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(lerp_color(background, foreground, 0.05).to_color());
+        canvas.draw_rect(Rect::from_wh(canvas_width, self.height), &paint);
+
+        // Every tab shows its title followed by a close button as wide as a cell is tall
+        let titles: Vec<String> = self.tabs.iter().map(|tab| tab_title(&tab.name)).collect();
+        let widths: Vec<f32> = titles
+            .iter()
+            .map(|title| {
+                title.chars().count().min(MAX_TAB_COLUMNS) as f32 * font_width
+                    + font_height
+                    + padding * 3.0
+            })
+            .collect();
+        let total_width: f32 = widths.iter().sum();
+        let shrink = (canvas_width / total_width).min(1.0);
+
+        let mut left = 0.0;
+        for width in widths {
+            let tab = Rect::from_xywh(left, 0.0, width * shrink, self.height);
+            let close_button = Rect::from_xywh(
+                tab.right - padding - font_height,
+                padding,
+                font_height,
+                font_height,
+            );
+            self.regions.push(TabRegion { tab, close_button });
+            left += width * shrink;
+        }
+
+        // The dragged tab follows the pointer and is drawn over the others
+        let dragged = self
+            .drag
+            .as_ref()
+            .filter(|drag| drag.moved && drag.index < self.tabs.len())
+            .map(|drag| (drag.index, self.pointer.x - drag.start_x));
+        let mut order: Vec<usize> = (0..self.tabs.len()).collect();
+        if let Some((index, _)) = dragged {
+            order.retain(|other| *other != index);
+            order.push(index);
+        }
+
+        for index in order {
+            let offset = match dragged {
+                Some((dragged_index, offset)) if dragged_index == index => offset,
+                _ => 0.0,
+            };
+            let tab = self.regions[index].tab.with_offset((offset, 0.0));
+            let close_button = self.regions[index].close_button.with_offset((offset, 0.0));
+            let is_current = self.tabs[index].tab == self.current_tab;
+
+            // The current tab has the editor background, so it looks connected to the grid below
+            let tab_background = if is_current {
+                background
+            } else {
+                lerp_color(background, foreground, 0.12)
+            };
+            paint.set_color(tab_background.to_color());
+            canvas.draw_rect(tab, &paint);
+            if is_current {
+                paint.set_color(lerp_color(background, foreground, 0.7).to_color());
+                canvas.draw_rect(
+                    Rect::from_xywh(tab.left, 0.0, tab.width(), ACCENT_HEIGHT * scale_factor),
+                    &paint,
+                );
+            }
+
+            canvas.save();
+            canvas.clip_rect(
+                Rect::from_ltrb(tab.left, tab.top, close_button.left, tab.bottom),
+                None,
+                Some(true),
+            );
+            let title_color = if is_current {
+                foreground
+            } else {
+                lerp_color(background, foreground, 0.6)
+            };
+            paint.set_color(title_color.to_color());
+            let y_adjustment = grid_renderer.shaper.y_adjustment() as f32;
+            for blob in grid_renderer
+                .shaper
+                .shape_cached(titles[index].clone(), false, false)
+                .iter()
+            {
+                canvas.draw_text_blob(blob, (tab.left + padding, padding + y_adjustment), &paint);
+            }
+            canvas.restore();
+
+            if contains(&close_button, self.pointer) && dragged.is_none() {
+                paint.set_color(lerp_color(tab_background, foreground, 0.2).to_color());
+                canvas.draw_circle(close_button.center(), close_button.width() / 2.0, &paint);
+            }
+            let cross = close_button.with_inset((font_height / 3.0, font_height / 3.0));
+            paint.set_color(title_color.to_color());
+            paint.set_stroke_width(scale_factor.max(1.0));
+            canvas.draw_line((cross.left, cross.top), (cross.right, cross.bottom), &paint);
+            canvas.draw_line((cross.right, cross.top), (cross.left, cross.bottom), &paint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_title() {
+        assert_eq!(tab_title("/home/user/project/src/main.rs"), "main.rs");
+        assert_eq!(tab_title("term://~//1234:/bin/zsh"), "zsh");
+        assert_eq!(tab_title(""), "[No Name]");
+    }
+
+    #[test]
+    fn test_drop_index() {
+        let centers = [50.0, 150.0, 250.0, 350.0];
+        assert_eq!(drop_index(&centers, 0, 300.0), 2);
+        assert_eq!(drop_index(&centers, 3, 10.0), 0);
+        assert_eq!(drop_index(&centers, 1, 160.0), 1);
+        assert_eq!(drop_index(&centers, 1, 1000.0), 3);
+    }
+
+    #[test]
+    fn test_tabmove_argument() {
+        // Moving the first of four tabs one slot right puts it after tab 2
+        assert_eq!(tabmove_argument(0, 1), 2);
+        // Moving it to the end puts it after tab 4
+        assert_eq!(tabmove_argument(0, 3), 4);
+        // Moving the last tab to the front and to the second slot
+        assert_eq!(tabmove_argument(3, 0), 0);
+        assert_eq!(tabmove_argument(3, 1), 1);
+    }
+}
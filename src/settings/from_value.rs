@@ -1,16 +1,17 @@
 use super::Value;
-use log::error;
 
 // Trait to allow for conversion from rmpv::Value to any other data type.
 // Note: Feel free to implement this trait for custom types in each subsystem.
 // The reverse conversion (MyType->Value) can be performed by implementing `From<MyType> for Value`
+// On failure the value is left unchanged, and the error describes the expected type and the value
+// received so it can be reported back to the user
 pub trait ParseFromValue {
-    fn parse_from_value(&mut self, value: Value);
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String>;
 }
 
 // FromValue implementations for most typical types
 impl ParseFromValue for f32 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
         if value.is_f64() {
             *self = value.as_f64().unwrap() as f32;
         } else if value.is_i64() {
@@ -18,60 +19,66 @@ impl ParseFromValue for f32 {
         } else if value.is_u64() {
             *self = value.as_u64().unwrap() as f32;
         } else {
-            error!("Setting expected an f32, but received {:?}", value);
+            return Err(format!("expected an f32, but received {}", value));
         }
+        Ok(())
     }
 }
 
 impl ParseFromValue for u64 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
         if value.is_u64() {
             *self = value.as_u64().unwrap();
         } else {
-            error!("Setting expected a u64, but received {:?}", value);
+            return Err(format!("expected a u64, but received {}", value));
         }
+        Ok(())
     }
 }
 
 impl ParseFromValue for u32 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
         if value.is_u64() {
             *self = value.as_u64().unwrap() as u32;
         } else {
-            error!("Setting expected a u32, but received {:?}", value);
+            return Err(format!("expected a u32, but received {}", value));
         }
+        Ok(())
     }
 }
 
 impl ParseFromValue for i32 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
         if value.is_i64() {
             *self = value.as_i64().unwrap() as i32;
         } else {
-            error!("Setting expected an i32, but received {:?}", value);
+            return Err(format!("expected an i32, but received {}", value));
         }
+        Ok(())
     }
 }
 
 impl ParseFromValue for String {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
         if value.is_str() {
             *self = String::from(value.as_str().unwrap());
         } else {
-            error!("Setting expected a string, but received {:?}", value);
+            return Err(format!("expected a string, but received {}", value));
         }
+        Ok(())
     }
 }
 
 impl ParseFromValue for bool {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> Result<(), String> {
         if value.is_bool() {
             *self = value.as_bool().unwrap();
         } else if value.is_u64() {
             *self = value.as_u64().unwrap() != 0;
         } else {
-            error!("Setting expected a bool or 0/1, but received {:?}", value);
+            return Err(format!("expected a bool or 0/1, but received {}", value));
         }
+        Ok(())
     }
 }
 
@@ -90,15 +97,15 @@ mod tests {
         let v2p = -1.0;
         let v3p = std::u64::MAX as f32;
 
-        v0.parse_from_value(v1);
+        v0.parse_from_value(v1).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
-        v0.parse_from_value(v2);
+        v0.parse_from_value(v2).unwrap();
         assert_eq!(v0, v2p, "v0 should equal {} but is actually {}", v2p, v0);
-        v0.parse_from_value(v3);
+        v0.parse_from_value(v3).unwrap();
         assert_eq!(v0, v3p, "v0 should equal {} but is actually {}", v3p, v0);
 
-        // This is a noop and prints an error
-        v0.parse_from_value(Value::from("asd"));
+        // This is a noop and returns an error
+        assert!(v0.parse_from_value(Value::from("asd")).is_err());
         assert_eq!(v0, v3p, "v0 should equal {} but is actually {}", v3p, v0);
     }
 
@@ -108,11 +115,11 @@ mod tests {
         let v1 = Value::from(std::u64::MAX);
         let v1p = std::u64::MAX;
 
-        v0.parse_from_value(v1);
+        v0.parse_from_value(v1).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
 
-        // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        // This is a noop and returns an error
+        assert!(v0.parse_from_value(Value::from(-1)).is_err());
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
     }
 
@@ -122,11 +129,11 @@ mod tests {
         let v1 = Value::from(std::u64::MAX);
         let v1p = std::u64::MAX as u32;
 
-        v0.parse_from_value(v1);
+        v0.parse_from_value(v1).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
 
-        // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        // This is a noop and returns an error
+        assert!(v0.parse_from_value(Value::from(-1)).is_err());
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
     }
 
@@ -136,11 +143,11 @@ mod tests {
         let v1 = Value::from(std::i64::MAX);
         let v1p = std::i64::MAX as i32;
 
-        v0.parse_from_value(v1);
+        v0.parse_from_value(v1).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
 
-        // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        // -1 is a valid i32, and the same value i64::MAX is truncated to
+        v0.parse_from_value(Value::from(-1)).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
     }
 
@@ -150,11 +157,11 @@ mod tests {
         let v1 = Value::from("bar");
         let v1p = "bar";
 
-        v0.parse_from_value(v1);
+        v0.parse_from_value(v1).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
 
-        // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        // This is a noop and returns an error
+        assert!(v0.parse_from_value(Value::from(-1)).is_err());
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
     }
 
@@ -168,15 +175,15 @@ mod tests {
         let v3 = Value::from(1);
         let v3p = true;
 
-        v0.parse_from_value(v1);
+        v0.parse_from_value(v1).unwrap();
         assert_eq!(v0, v1p, "v0 should equal {} but is actually {}", v1p, v0);
-        v0.parse_from_value(v2);
+        v0.parse_from_value(v2).unwrap();
         assert_eq!(v0, v2p, "v0 should equal {} but is actually {}", v2p, v0);
-        v0.parse_from_value(v3);
+        v0.parse_from_value(v3).unwrap();
         assert_eq!(v0, v3p, "v0 should equal {} but is actually {}", v3p, v0);
 
-        // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        // This is a noop and returns an error
+        assert!(v0.parse_from_value(Value::from(-1)).is_err());
         assert_eq!(v0, v3p, "v0 should equal {} but is actually {}", v3p, v0);
     }
 }
@@ -1,8 +1,8 @@
 mod from_value;
 mod window_geometry;
 
-use log::trace;
-use nvim_rs::Neovim;
+use log::{error, trace};
+use nvim_rs::{error::CallError, Neovim};
 use parking_lot::RwLock;
 use rmpv::Value;
 use std::{
@@ -11,7 +11,7 @@ use std::{
     convert::TryInto,
 };
 
-use crate::{bridge::TxWrapper, error_handling::ResultPanicExplanation};
+use crate::bridge::TxWrapper;
 pub use from_value::ParseFromValue;
 pub use window_geometry::{
     load_last_window_settings, parse_window_geometry, save_window_geometry,
@@ -26,8 +26,9 @@ pub trait SettingGroup {
     fn register(&self);
 }
 
-// Function types to handle settings updates
-type UpdateHandlerFunc = fn(Value);
+// Function types to handle settings updates. Updates fail with a message naming the setting when
+// the value has the wrong type or is out of range
+type UpdateHandlerFunc = fn(Value) -> Result<(), String>;
 type ReaderFunc = fn() -> Value;
 
 // The Settings struct acts as a global container where each of Neovide's subsystems can store
@@ -91,7 +92,11 @@ impl Settings {
             let variable_name = format!("neovide_{}", name);
             match nvim.get_var(&variable_name).await {
                 Ok(value) => {
-                    self.listeners.read().get(&name).unwrap()(value);
+                    let result = self.listeners.read().get(&name).unwrap()(value);
+                    if let Err(message) = result {
+                        error!("{}", message);
+                        nvim.err_writeln(&message).await.ok();
+                    }
                 }
                 Err(error) => {
                     trace!("Initial value load failed for {}: {}", name, error);
@@ -102,40 +107,51 @@ impl Settings {
         }
     }
 
-    pub async fn setup_changed_listeners(&self, nvim: &Neovim<TxWrapper>) {
+    // Every attached gui gets its own watcher, named after its channel, so they don't replace each
+    // other. A watcher whose channel has closed removes itself the next time it fires
+    pub async fn setup_changed_listeners(
+        &self,
+        nvim: &Neovim<TxWrapper>,
+        channel: u64,
+    ) -> Result<(), Box<CallError>> {
         let keys: Vec<String> = self.listeners.read().keys().cloned().collect();
 
         for name in keys {
-            let vimscript = format!(
-                concat!(
-                    "exe \"",
-                    "fun! NeovideNotify{0}Changed(d, k, z)\n",
-                    "call rpcnotify(1, 'setting_changed', '{0}', g:neovide_{0})\n",
-                    "endf\n",
-                    "call dictwatcheradd(g:, 'neovide_{0}', 'NeovideNotify{0}Changed')\"",
-                ),
-                name
-            );
-            nvim.command(&vimscript)
-                .await
-                .unwrap_or_explained_panic(&format!(
-                    "Could not setup setting notifier for {}",
-                    name
-                ));
+            nvim.command(&changed_listener_vimscript(&name, channel))
+                .await?;
         }
+        Ok(())
     }
 
-    pub fn handle_changed_notification(&self, arguments: Vec<Value>) {
+    pub fn handle_changed_notification(&self, arguments: Vec<Value>) -> Result<(), String> {
         let mut arguments = arguments.into_iter();
         let (name, value) = (arguments.next().unwrap(), arguments.next().unwrap());
 
         let name: Result<String, _> = name.try_into();
         let name = name.unwrap();
 
-        self.listeners.read().get(&name).unwrap()(value);
+        self.listeners.read().get(&name).unwrap()(value)
     }
 }
 
+fn changed_listener_vimscript(name: &str, channel: u64) -> String {
+    format!(
+        concat!(
+            "exe \"",
+            "fun! NeovideNotify{0}Changed{1}(d, k, z)\n",
+            "try\n",
+            "call rpcnotify({1}, 'setting_changed', '{0}', g:neovide_{0})\n",
+            "catch\n",
+            "call dictwatcherdel(g:, 'neovide_{0}', 'NeovideNotify{0}Changed{1}')\n",
+            "endtry\n",
+            "endf\n",
+            "silent! call dictwatcherdel(g:, 'neovide_{0}', 'NeovideNotify{0}Changed{1}')\n",
+            "call dictwatcheradd(g:, 'neovide_{0}', 'NeovideNotify{0}Changed{1}')\"",
+        ),
+        name, channel
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use async_trait::async_trait;
@@ -145,6 +161,7 @@ mod tests {
     use crate::{
         bridge::{create, create_nvim_command},
         cmd_line::CmdLineSettings,
+        error_handling::ResultPanicExplanation,
     };
 
     #[derive(Clone)]
@@ -163,13 +180,76 @@ mod tests {
         }
     }
 
+    #[derive(Clone, SettingGroup)]
+    #[setting_prefix = "validation_test"]
+    struct ValidatedSettings {
+        #[setting(min = 0.0, max = 1.0, clamp)]
+        opacity: f32,
+        #[setting(min = 1)]
+        rate: u64,
+        #[setting(allowed("left", "right"))]
+        side: String,
+        speed: f32,
+    }
+
+    impl Default for ValidatedSettings {
+        fn default() -> Self {
+            Self {
+                opacity: 0.5,
+                rate: 60,
+                side: "left".to_owned(),
+                speed: 1.0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_setting_validation() {
+        ValidatedSettings::register();
+        let update = |name: &str, value: Value| SETTINGS.listeners.read().get(name).unwrap()(value);
+
+        assert!(update("validation_test_opacity", Value::from(1.5)).is_ok());
+        assert_eq!(SETTINGS.get::<ValidatedSettings>().opacity, 1.0);
+        let message = update("validation_test_opacity", Value::from("0.8")).unwrap_err();
+        assert!(message.contains("g:neovide_validation_test_opacity"));
+        assert!(message.contains("\"0.8\""));
+        assert_eq!(SETTINGS.get::<ValidatedSettings>().opacity, 1.0);
+
+        // NaN is neither below nor above a bound, and is refused even where values are clamped
+        let message = update("validation_test_opacity", Value::from(f64::NAN)).unwrap_err();
+        assert!(message.contains("NaN"));
+        assert_eq!(SETTINGS.get::<ValidatedSettings>().opacity, 1.0);
+        assert!(update("validation_test_speed", Value::from(f64::NAN)).is_err());
+        assert_eq!(SETTINGS.get::<ValidatedSettings>().speed, 1.0);
+
+        assert!(update("validation_test_rate", Value::from(0)).is_err());
+        assert_eq!(SETTINGS.get::<ValidatedSettings>().rate, 60);
+
+        assert!(update("validation_test_side", Value::from("up")).is_err());
+        assert!(update("validation_test_side", Value::from("right")).is_ok());
+        assert_eq!(SETTINGS.get::<ValidatedSettings>().side, "right");
+    }
+
+    #[test]
+    fn test_changed_listener_uses_channel() {
+        let vimscript = changed_listener_vimscript("foo", 3);
+
+        assert!(vimscript.contains("fun! NeovideNotifyfooChanged3(d, k, z)"));
+        assert!(vimscript.contains("rpcnotify(3, 'setting_changed', 'foo', g:neovide_foo)"));
+        assert!(vimscript.contains("dictwatcheradd(g:, 'neovide_foo', 'NeovideNotifyfooChanged3')"));
+        // Listeners of other guis are left alone
+        assert_ne!(vimscript, changed_listener_vimscript("foo", 4));
+    }
+
     #[test]
     fn test_set_setting_handlers() {
         let settings = Settings::new();
 
         let property_name = "foo";
 
-        fn noop_update(_v: Value) {}
+        fn noop_update(_v: Value) -> Result<(), String> {
+            Ok(())
+        }
 
         fn noop_read() -> Value {
             Value::Nil
@@ -258,7 +338,9 @@ mod tests {
             .unwrap_or_explained_panic("Could not locate or start the neovim process");
         nvim.set_var(&v4, Value::from(v2.clone())).await.ok();
 
-        fn noop_update(_v: Value) {}
+        fn noop_update(_v: Value) -> Result<(), String> {
+            Ok(())
+        }
 
         fn noop_read() -> Value {
             Value::from("baz".to_string())
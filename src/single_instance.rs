@@ -0,0 +1,332 @@
+//! Single instance mode. The first Neovide started with `--remote` listens on a per-user socket
+//! (a named pipe on windows). Later invocations forward the files they were asked to open to it
+//! and exit instead of starting a new window and neovim process.
+
+use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::{error, info, trace};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    bridge::{ParallelCommand, UiCommand},
+    cmd_line::CmdLineSettings,
+    event_aggregator::EVENT_AGGREGATOR,
+    settings::SETTINGS,
+};
+
+// Set once this instance owns the socket, so only the listening instance removes it on exit
+static LISTENING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenFilesRequest {
+    cwd: String,
+    files: Vec<String>,
+    no_tabs: bool,
+}
+
+impl OpenFilesRequest {
+    // Files are resolved against the working directory of the invocation that sent them, since
+    // the running instance may have a different one.
+    fn resolved_files(&self) -> Vec<String> {
+        let cwd = Path::new(&self.cwd);
+        self.files
+            .iter()
+            .map(|file| cwd.join(file).to_string_lossy().into_owned())
+            .collect()
+    }
+}
+
+#[cfg(windows)]
+fn user_name() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "default".to_owned())
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // Safe as getuid has no preconditions and can't fail
+    unsafe { libc::getuid() }
+}
+
+// Another local user could otherwise create the socket or its directory first and receive the
+// files of every later invocation
+#[cfg(unix)]
+fn check_owned_by_current_user(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    if std::fs::symlink_metadata(path)?.uid() != current_uid() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is owned by another user", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+// Creates the directory holding the socket, accessible only to the current user, or checks that
+// an existing one is
+#[cfg(unix)]
+fn create_private_directory(directory: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(directory) {
+        Err(error) if error.kind() != io::ErrorKind::AlreadyExists => return Err(error),
+        _ => {}
+    }
+
+    check_owned_by_current_user(directory)?;
+    let metadata = std::fs::symlink_metadata(directory)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory only accessible to its owner",
+                directory.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Without a runtime dir, as on macOS, the directory is created in the shared temporary directory
+// and named after the uid, which unlike $USER can't be chosen by the caller
+#[cfg(unix)]
+fn socket_path() -> io::Result<String> {
+    let directory = match dirs::runtime_dir() {
+        Some(runtime_dir) => runtime_dir.join("neovide"),
+        None => env::temp_dir().join(format!("neovide-{}", current_uid())),
+    };
+    create_private_directory(&directory)?;
+    Ok(directory
+        .join("neovide.sock")
+        .to_string_lossy()
+        .into_owned())
+}
+
+#[cfg(windows)]
+fn socket_path() -> io::Result<String> {
+    Ok(format!(r"\\.\pipe\neovide-{}", user_name()))
+}
+
+#[cfg(unix)]
+fn connect(path: &str) -> std::io::Result<impl Write> {
+    check_owned_by_current_user(Path::new(path))?;
+    std::os::unix::net::UnixStream::connect(path)
+}
+
+#[cfg(windows)]
+fn connect(path: &str) -> std::io::Result<impl Write> {
+    std::fs::OpenOptions::new().write(true).open(path)
+}
+
+/// Sends the files from the command line to an already running instance.
+///
+/// # Returns
+/// `bool` indicating whether a running instance accepted them, in which case this process
+/// should exit.
+pub fn forward_to_running_instance() -> bool {
+    let settings = SETTINGS.get::<CmdLineSettings>();
+    let request = OpenFilesRequest {
+        cwd: env::current_dir()
+            .map(|cwd| cwd.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        files: settings.files_to_open,
+        no_tabs: settings.no_tabs,
+    };
+
+    let path = match socket_path() {
+        Ok(path) => path,
+        Err(error) => {
+            error!("Could not locate the single instance socket: {}", error);
+            return false;
+        }
+    };
+    match connect(&path) {
+        Ok(mut stream) => {
+            let message = serde_json::to_vec(&request).expect("Could not serialize request");
+            if let Err(error) = stream.write_all(&message) {
+                error!("Could not forward files to running instance: {}", error);
+                return false;
+            }
+            info!("Forwarded {:?} to running instance", request.files);
+            true
+        }
+        Err(error) => {
+            trace!("No running instance at {}: {}", path, error);
+            false
+        }
+    }
+}
+
+async fn handle_client<S: AsyncRead + Unpin>(mut stream: S) {
+    let mut message = String::new();
+    if let Err(error) = stream.read_to_string(&mut message).await {
+        error!("Could not read single instance request: {}", error);
+        return;
+    }
+
+    match serde_json::from_str::<OpenFilesRequest>(&message) {
+        Ok(request) => {
+            let files = request.resolved_files();
+            if !files.is_empty() {
+                EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::OpenFiles {
+                    files,
+                    no_tabs: request.no_tabs,
+                }));
+            }
+        }
+        Err(error) => error!("Invalid single instance request {:?}: {}", message, error),
+    }
+}
+
+/// Listens for files forwarded by later invocations and opens them in this instance
+#[cfg(unix)]
+pub async fn listen() {
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = match socket_path() {
+        Ok(path) => path,
+        Err(error) => {
+            error!("Could not locate the single instance socket: {}", error);
+            return;
+        }
+    };
+    // A socket left behind by an instance which didn't shut down cleanly can't be connected to
+    // anymore and has to be removed before binding
+    if Path::new(&path).exists() {
+        if let Err(error) = check_owned_by_current_user(Path::new(&path)) {
+            error!("Could not listen for other instances: {}", error);
+            return;
+        }
+        if UnixStream::connect(&path).await.is_err() {
+            std::fs::remove_file(&path).ok();
+        }
+    }
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(error) => {
+            error!(
+                "Could not listen for other instances at {}: {}",
+                path, error
+            );
+            return;
+        }
+    };
+    LISTENING.store(true, Ordering::Relaxed);
+    info!("Listening for other instances at {}", path);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_client(stream));
+            }
+            Err(error) => error!("Could not accept single instance connection: {}", error),
+        }
+    }
+}
+
+/// Removes the socket of the listening instance. Neovide exits with `process::exit`, which skips
+/// destructors, so this is called explicitly on shutdown. Named pipes go away with the process.
+pub fn shutdown() {
+    #[cfg(unix)]
+    if LISTENING.swap(false, Ordering::Relaxed) {
+        if let Ok(path) = socket_path() {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+/// Listens for files forwarded by later invocations and opens them in this instance
+#[cfg(windows)]
+pub async fn listen() {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let path = match socket_path() {
+        Ok(path) => path,
+        Err(error) => {
+            error!("Could not locate the single instance socket: {}", error);
+            return;
+        }
+    };
+    let mut server = match ServerOptions::new().first_pipe_instance(true).create(&path) {
+        Ok(server) => server,
+        Err(error) => {
+            error!(
+                "Could not listen for other instances at {}: {}",
+                path, error
+            );
+            return;
+        }
+    };
+    LISTENING.store(true, Ordering::Relaxed);
+    info!("Listening for other instances at {}", path);
+
+    loop {
+        if let Err(error) = server.connect().await {
+            error!("Could not accept single instance connection: {}", error);
+            continue;
+        }
+
+        let connected = server;
+        server = match ServerOptions::new().create(&path) {
+            Ok(server) => server,
+            Err(error) => {
+                error!(
+                    "Could not listen for other instances at {}: {}",
+                    path, error
+                );
+                return;
+            }
+        };
+        tokio::spawn(handle_client(connected));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolved_files() {
+        let request = OpenFilesRequest {
+            cwd: "/home/user/project".to_owned(),
+            files: vec!["src/main.rs".to_owned(), "/etc/hosts".to_owned()],
+            no_tabs: false,
+        };
+
+        assert_eq!(
+            request.resolved_files(),
+            vec!["/home/user/project/src/main.rs", "/etc/hosts"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_private_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = env::temp_dir().join(format!("neovide-test-{}", std::process::id()));
+        create_private_directory(&directory).unwrap();
+        assert_eq!(
+            std::fs::metadata(&directory).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        // An existing private directory is reused
+        create_private_directory(&directory).unwrap();
+
+        // One other users can access is refused
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(create_private_directory(&directory).is_err());
+
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
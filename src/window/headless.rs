@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use log::{error, info};
+use skia_safe::{EncodedImageFormat, Surface};
+
+use crate::{
+    cmd_line::CmdLineSettings, editor::DrawCommand, error_handling::ResultPanicExplanation,
+    renderer::Renderer, running_tracker::*, settings::SETTINGS,
+};
+
+// Snapshots should only depend on the received draw commands and not on timing, so every frame
+// advances animations by a whole second. With the default settings the longest animations are the
+// cursor trail, whose back corners need about 0.7s for a jump across a large window, and the 0.3s
+// scroll animation. Recordings meant for snapshots shouldn't configure longer ones.
+pub const FRAME_DT: f32 = 1.0;
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// Draws every batch of draw commands sent on flush onto a raster surface instead of a window and
+// writes the result to a numbered png in the output directory
+pub fn run_headless(output_dir: &Path) {
+    fs::create_dir_all(output_dir)
+        .unwrap_or_explained_panic("Could not create headless output directory");
+
+    let mut renderer = Renderer::new(1.0);
+    let mut surface = create_surface(&renderer);
+    let mut frame_index = 0;
+
+    loop {
+        match renderer.batched_draw_command_receiver.try_recv() {
+            Ok(mut draw_commands) => {
+                let batch_len = draw_commands.len();
+                draw_commands.retain(|command| !matches!(command, DrawCommand::EndOfReplay));
+                let replay_finished = draw_commands.len() != batch_len;
+
+                if !draw_commands.is_empty() {
+                    if renderer.draw_batch(surface.canvas(), draw_commands, FRAME_DT) {
+                        // The grid keeps its size, so the surface has to follow the cell size
+                        surface = create_surface(&renderer);
+                        renderer.draw_batch(surface.canvas(), Vec::new(), FRAME_DT);
+                    }
+
+                    frame_index += 1;
+                    let path = snapshot_path(output_dir, frame_index);
+                    if let Err(error) = write_snapshot(&mut surface, &path) {
+                        error!("Could not write snapshot {}: {}", path.display(), error);
+                    }
+                }
+
+                if replay_finished {
+                    info!("Replay rendered to {} frames", frame_index);
+                    std::process::exit(0);
+                }
+            }
+            // Pending batches are still drawn after neovim exits so the last frames aren't lost
+            Err(_) if !RUNNING_TRACKER.is_running() => {
+                info!("Headless rendering finished after {} frames", frame_index);
+                std::process::exit(RUNNING_TRACKER.exit_code());
+            }
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+fn create_surface(renderer: &Renderer) -> Surface {
+    let geometry = SETTINGS.get::<CmdLineSettings>().geometry;
+    let size = renderer.grid_renderer.convert_grid_to_physical(geometry);
+    Surface::new_raster_n32_premul((size.width as i32, size.height as i32))
+        .expect("Could not create raster surface")
+}
+
+fn snapshot_path(output_dir: &Path, frame_index: u64) -> PathBuf {
+    output_dir.join(format!("frame-{:05}.png", frame_index))
+}
+
+pub fn write_snapshot(surface: &mut Surface, path: &Path) -> Result<(), String> {
+    let data = surface
+        .image_snapshot()
+        .encode_to_data(EncodedImageFormat::PNG)
+        .ok_or("Could not encode frame")?;
+    fs::write(path, data.as_bytes()).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_path() {
+        assert_eq!(
+            snapshot_path(Path::new("out"), 12),
+            Path::new("out").join("frame-00012.png")
+        );
+    }
+}
@@ -122,6 +122,10 @@ impl KeyboardManager {
         }
     }
 
+    pub fn is_ctrl_pressed(&self) -> bool {
+        self.ctrl
+    }
+
     fn should_ignore_input(&self) -> bool {
         let settings = SETTINGS.get::<KeyboardSettings>();
         self.ignore_input_this_frame || (self.logo && !settings.use_logo)
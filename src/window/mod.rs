@@ -1,9 +1,13 @@
+mod headless;
 mod keyboard_manager;
 mod mouse_manager;
 mod renderer;
 mod settings;
 
-use std::time::{Duration, Instant};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use glutin::{
     self,
@@ -11,9 +15,9 @@ use glutin::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::{self, Fullscreen, Icon},
-    ContextBuilder, GlProfile, WindowedContext,
 };
 use log::trace;
+use rmpv::Value;
 use tokio::sync::mpsc::UnboundedReceiver;
 
 #[cfg(target_os = "macos")]
@@ -25,22 +29,27 @@ use glutin::platform::unix::WindowBuilderExtUnix;
 use image::{load_from_memory, GenericImageView, Pixel};
 use keyboard_manager::KeyboardManager;
 use mouse_manager::MouseManager;
-use renderer::SkiaRenderer;
+use renderer::{create_skia_renderer, SkiaRenderer};
 
 use crate::{
-    bridge::{ParallelCommand, UiCommand},
+    bridge::{
+        metrics_value, size_value, ApiResponder, GridRegion, GuiEvent, GuiMetrics, ParallelCommand,
+        UiCommand, WindowRequest,
+    },
     cmd_line::CmdLineSettings,
     dimensions::Dimensions,
-    editor::EditorCommand,
+    editor::{EditorCommand, MouseShape},
     event_aggregator::EVENT_AGGREGATOR,
     frame::Frame,
     redraw_scheduler::REDRAW_SCHEDULER,
-    renderer::Renderer,
+    renderer::{Renderer, RendererSettings},
     running_tracker::*,
     settings::{
         load_last_window_settings, save_window_geometry, PersistentWindowSettings, SETTINGS,
     },
+    single_instance,
 };
+pub use headless::{run_headless, write_snapshot, FRAME_DT};
 pub use settings::{KeyboardSettings, WindowSettings};
 
 static ICON: &[u8] = include_bytes!("../../assets/neovide.ico");
@@ -52,25 +61,33 @@ const MIN_WINDOW_HEIGHT: u64 = 6;
 pub enum WindowCommand {
     TitleChanged(String),
     SetMouseEnabled(bool),
+    SetMouseShape(Option<MouseShape>),
+    SetBusy(bool),
     ListAvailableFonts,
+    ApiRequest(WindowRequest, ApiResponder),
 }
 
 pub struct GlutinWindowWrapper {
-    windowed_context: WindowedContext<glutin::PossiblyCurrent>,
     skia_renderer: SkiaRenderer,
     renderer: Renderer,
     keyboard_manager: KeyboardManager,
     mouse_manager: MouseManager,
     title: String,
     fullscreen: bool,
+    // The scale_factor setting, applied on top of the scale factor of the monitor
+    scale_factor: f32,
     saved_inner_size: PhysicalSize<u32>,
+    saved_tabline_height: f32,
     saved_grid_size: Option<Dimensions>,
+    published_metrics: Option<GuiMetrics>,
+    // Screenshots are taken while drawing the next frame, before it is presented
+    pending_screenshots: Vec<(PathBuf, ApiResponder)>,
     window_command_receiver: UnboundedReceiver<WindowCommand>,
 }
 
 impl GlutinWindowWrapper {
     pub fn toggle_fullscreen(&mut self) {
-        let window = self.windowed_context.window();
+        let window = self.skia_renderer.window();
         if self.fullscreen {
             window.set_fullscreen(None);
         } else {
@@ -79,14 +96,34 @@ impl GlutinWindowWrapper {
         }
 
         self.fullscreen = !self.fullscreen;
+        EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::GuiEvent(
+            GuiEvent::FullscreenChanged(self.fullscreen),
+        )));
     }
 
     pub fn synchronize_settings(&mut self) {
-        let fullscreen = { SETTINGS.get::<WindowSettings>().fullscreen };
+        let settings = SETTINGS.get::<WindowSettings>();
 
-        if self.fullscreen != fullscreen {
+        if self.fullscreen != settings.fullscreen {
             self.toggle_fullscreen();
         }
+
+        if (self.scale_factor - settings.scale_factor).abs() > f32::EPSILON
+            && settings.scale_factor > 0.0
+        {
+            self.scale_factor = settings.scale_factor;
+            let window = self.skia_renderer.window();
+            let (monitor_scale_factor, inner_size) = (window.scale_factor(), window.inner_size());
+            self.handle_scale_factor_update(monitor_scale_factor);
+            self.handle_new_grid_size(inner_size);
+        }
+
+        let line_space = SETTINGS.get::<RendererSettings>().line_space;
+        if self.renderer.update_line_space(line_space) {
+            let inner_size = self.skia_renderer.window().inner_size();
+            self.handle_new_grid_size(inner_size);
+            EVENT_AGGREGATOR.send(EditorCommand::RedrawScreen);
+        }
     }
 
     #[allow(clippy::needless_collect)]
@@ -97,14 +134,27 @@ impl GlutinWindowWrapper {
                 WindowCommand::SetMouseEnabled(mouse_enabled) => {
                     self.mouse_manager.enabled = mouse_enabled
                 }
+                WindowCommand::SetMouseShape(mouse_shape) => {
+                    self.mouse_manager.mode_shape = mouse_shape;
+                    self.mouse_manager
+                        .update_pointer_icon(self.skia_renderer.window());
+                }
+                WindowCommand::SetBusy(busy) => {
+                    self.mouse_manager.busy = busy;
+                    self.mouse_manager
+                        .update_pointer_icon(self.skia_renderer.window());
+                }
                 WindowCommand::ListAvailableFonts => self.send_font_names(),
+                WindowCommand::ApiRequest(request, responder) => {
+                    self.handle_api_request(request, responder)
+                }
             }
         }
     }
 
     pub fn handle_title_changed(&mut self, new_title: String) {
         self.title = new_title;
-        self.windowed_context.window().set_title(&self.title);
+        self.skia_renderer.window().set_title(&self.title);
     }
 
     pub fn send_font_names(&self) {
@@ -114,8 +164,78 @@ impl GlutinWindowWrapper {
         )));
     }
 
+    fn handle_api_request(&mut self, request: WindowRequest, responder: ApiResponder) {
+        let result = match request {
+            WindowRequest::Screenshot(path) => {
+                // The back buffer is undefined once a frame was presented, so the surface can't
+                // be read back until the next frame has been drawn onto it
+                self.pending_screenshots.push((path, responder));
+                REDRAW_SCHEDULER.queue_next_frame();
+                return;
+            }
+            WindowRequest::CellSize => {
+                let font_dimensions = self.renderer.grid_renderer.font_dimensions;
+                Ok(size_value(font_dimensions.width, font_dimensions.height))
+            }
+            WindowRequest::WindowSize => {
+                let size = self.skia_renderer.window().inner_size();
+                Ok(size_value(size.width as u64, size.height as u64))
+            }
+            WindowRequest::Metrics => Ok(metrics_value(&self.gui_metrics(), &self.grid_regions())),
+            WindowRequest::ListFonts => Ok(Value::from(
+                self.renderer
+                    .font_names()
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<Value>>(),
+            )),
+        };
+        responder.respond(result);
+    }
+
+    fn gui_metrics(&self) -> GuiMetrics {
+        let font_dimensions = self.renderer.grid_renderer.font_dimensions;
+        let size = self.skia_renderer.window().inner_size();
+        GuiMetrics {
+            cell_width: font_dimensions.width,
+            cell_height: font_dimensions.height,
+            scale_factor: self.renderer.grid_renderer.scale_factor,
+            window_width: size.width as u64,
+            window_height: size.height as u64,
+            grid_top: self.renderer.tabline_height().ceil() as u64,
+        }
+    }
+
+    fn grid_regions(&self) -> Vec<GridRegion> {
+        self.renderer
+            .window_regions
+            .iter()
+            .map(|details| GridRegion {
+                grid: details.id,
+                x: details.region.left as f64,
+                y: details.region.top as f64,
+                width: details.region.width() as f64,
+                height: details.region.height() as f64,
+            })
+            .collect()
+    }
+
+    // Grid regions move every frame while windows animate, so only the metrics which change with
+    // the font, scale factor or window size are published. Plugins can ask for the regions with
+    // the neovide.metrics request
+    fn publish_metrics(&mut self) {
+        let metrics = self.gui_metrics();
+        if self.published_metrics.as_ref() != Some(&metrics) {
+            self.published_metrics = Some(metrics.clone());
+            EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::PublishMetrics(
+                metrics,
+            )));
+        }
+    }
+
     pub fn handle_quit(&mut self) {
-        if SETTINGS.get::<CmdLineSettings>().remote_tcp.is_none() {
+        let settings = SETTINGS.get::<CmdLineSettings>();
+        if settings.remote_tcp.is_none() && settings.server.is_none() && settings.replay.is_none() {
             EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::Quit));
         } else {
             RUNNING_TRACKER.quit("window closed");
@@ -137,7 +257,7 @@ impl GlutinWindowWrapper {
             &event,
             &self.keyboard_manager,
             &self.renderer,
-            &self.windowed_context,
+            self.skia_renderer.window(),
         );
         self.renderer.handle_event(&event);
         match event {
@@ -184,15 +304,19 @@ impl GlutinWindowWrapper {
     }
 
     pub fn draw_frame(&mut self, dt: f32) {
-        let window = self.windowed_context.window();
         let mut font_changed = false;
 
         if REDRAW_SCHEDULER.should_draw() || SETTINGS.get::<WindowSettings>().no_idle {
             font_changed = self.renderer.draw_frame(self.skia_renderer.canvas(), dt);
-            self.skia_renderer.gr_context.flush(None);
-            self.windowed_context.swap_buffers().unwrap();
+            for (path, responder) in self.pending_screenshots.drain(..) {
+                let result = write_snapshot(self.skia_renderer.surface(), &path);
+                responder.respond(result.map(|_| Value::Nil));
+            }
+            self.skia_renderer.present();
         }
 
+        let window = self.skia_renderer.window();
+
         // Wait until fonts are loaded, so we can set proper window size.
         if !self.renderer.grid_renderer.is_ready {
             return;
@@ -232,18 +356,32 @@ impl GlutinWindowWrapper {
             font_changed = false;
         }
 
-        if self.saved_inner_size != new_size || font_changed {
+        let tabline_height = self.renderer.tabline_height();
+        if self.saved_inner_size != new_size
+            || font_changed
+            || self.saved_tabline_height != tabline_height
+        {
             self.saved_inner_size = new_size;
+            self.saved_tabline_height = tabline_height;
             self.handle_new_grid_size(new_size);
-            self.skia_renderer.resize(&self.windowed_context);
+            self.skia_renderer.resize();
         }
+
+        self.publish_metrics();
     }
 
     fn handle_new_grid_size(&mut self, new_size: PhysicalSize<u32>) {
+        // The tabline is drawn above the grid, which gets the rest of the window
+        let grid_area = PhysicalSize::new(
+            new_size.width,
+            new_size
+                .height
+                .saturating_sub(self.renderer.tabline_height().ceil() as u32),
+        );
         let grid_size = self
             .renderer
             .grid_renderer
-            .convert_physical_to_grid(new_size);
+            .convert_physical_to_grid(grid_area);
 
         // Have a minimum size
         if grid_size.width < MIN_WINDOW_WIDTH || grid_size.height < MIN_WINDOW_HEIGHT {
@@ -259,12 +397,23 @@ impl GlutinWindowWrapper {
             width: grid_size.width,
             height: grid_size.height,
         }));
+        EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::GuiEvent(
+            GuiEvent::Resized {
+                columns: grid_size.width,
+                rows: grid_size.height,
+                width: new_size.width as u64,
+                height: new_size.height as u64,
+            },
+        )));
     }
 
     fn handle_scale_factor_update(&mut self, scale_factor: f64) {
         self.renderer
             .grid_renderer
-            .handle_scale_factor_update(scale_factor);
+            .handle_scale_factor_update(scale_factor * self.scale_factor as f64);
+        EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::GuiEvent(
+            GuiEvent::ScaleFactorChanged(self.renderer.grid_renderer.scale_factor),
+        )));
         EVENT_AGGREGATOR.send(EditorCommand::RedrawScreen);
     }
 }
@@ -337,17 +486,14 @@ pub fn create_window() {
         .with_app_id(cmd_line_settings.wayland_app_id)
         .with_class("neovide".to_string(), cmd_line_settings.x11_wm_class);
 
-    let windowed_context = ContextBuilder::new()
-        .with_pixel_format(24, 8)
-        .with_stencil_buffer(8)
-        .with_gl_profile(GlProfile::Core)
-        .with_vsync(false)
-        .with_srgb(cmd_line_settings.srgb)
-        .build_windowed(winit_window_builder, &event_loop)
-        .unwrap();
-    let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+    let skia_renderer = create_skia_renderer(
+        winit_window_builder,
+        &event_loop,
+        cmd_line_settings.srgb,
+        cmd_line_settings.software_render,
+    );
 
-    let window = windowed_context.window();
+    let window = skia_renderer.window();
 
     // Check that window is visible in some monitor, and reposition it if not.
     if let Some(current_monitor) = window.current_monitor() {
@@ -372,12 +518,11 @@ pub fn create_window() {
         }
     }
 
-    let scale_factor = windowed_context.window().scale_factor();
+    let user_scale_factor = SETTINGS.get::<WindowSettings>().scale_factor;
+    let scale_factor = window.scale_factor() * user_scale_factor as f64;
     let renderer = Renderer::new(scale_factor);
     let saved_inner_size = window.inner_size();
 
-    let skia_renderer = SkiaRenderer::new(&windowed_context);
-
     let window_command_receiver = EVENT_AGGREGATOR.register_event::<WindowCommand>();
 
     log::info!(
@@ -387,15 +532,18 @@ pub fn create_window() {
     );
 
     let mut window_wrapper = GlutinWindowWrapper {
-        windowed_context,
         skia_renderer,
         renderer,
         keyboard_manager: KeyboardManager::new(),
         mouse_manager: MouseManager::new(),
         title: String::from("Neovide"),
         fullscreen: false,
+        scale_factor: user_scale_factor,
         saved_inner_size,
+        saved_tabline_height: 0.0,
         saved_grid_size: None,
+        published_metrics: None,
+        pending_screenshots: Vec::new(),
         window_command_receiver,
     };
 
@@ -403,13 +551,14 @@ pub fn create_window() {
 
     event_loop.run(move |e, _window_target, control_flow| {
         if !RUNNING_TRACKER.is_running() {
-            let window = window_wrapper.windowed_context.window();
+            let window = window_wrapper.skia_renderer.window();
             save_window_geometry(
                 window.is_maximized(),
                 window_wrapper.saved_grid_size,
                 window.outer_position().ok(),
             );
 
+            single_instance::shutdown();
             std::process::exit(RUNNING_TRACKER.exit_code());
         }
 
@@ -1,6 +1,7 @@
 use std::{
     cmp::Ordering,
     collections::HashMap,
+    process::Command,
     time::{Duration, Instant},
 };
 
@@ -11,12 +12,14 @@ use glutin::{
         DeviceId, ElementState, Event, MouseButton, MouseScrollDelta, Touch, TouchPhase,
         WindowEvent,
     },
-    PossiblyCurrent, WindowedContext,
+    window::{CursorIcon, Window},
 };
+use log::error;
 use skia_safe::Rect;
 
 use crate::{
     bridge::{SerialCommand, UiCommand},
+    editor::MouseShape,
     event_aggregator::EVENT_AGGREGATOR,
     renderer::{Renderer, WindowDrawDetails},
     settings::SETTINGS,
@@ -51,6 +54,111 @@ fn to_grid_coords(
     )
 }
 
+// None hides the pointer
+fn mouse_shape_to_icon(mouse_shape: MouseShape) -> Option<CursorIcon> {
+    match mouse_shape {
+        MouseShape::Arrow | MouseShape::Pencil => Some(CursorIcon::Default),
+        MouseShape::Blank => None,
+        MouseShape::Beam => Some(CursorIcon::Text),
+        MouseShape::UpDown => Some(CursorIcon::NsResize),
+        MouseShape::UpDownSizing => Some(CursorIcon::RowResize),
+        MouseShape::LeftRight => Some(CursorIcon::EwResize),
+        MouseShape::LeftRightSizing => Some(CursorIcon::ColResize),
+        MouseShape::Busy => Some(CursorIcon::Wait),
+        MouseShape::No => Some(CursorIcon::NotAllowed),
+        MouseShape::Crosshair => Some(CursorIcon::Crosshair),
+        MouseShape::Hand => Some(CursorIcon::Grab),
+        MouseShape::Hand2 => Some(CursorIcon::Hand),
+        MouseShape::Question => Some(CursorIcon::Help),
+        MouseShape::RightUpArrow | MouseShape::UpArrow => Some(CursorIcon::Arrow),
+    }
+}
+
+// Separators and status lines are drawn on the root grid in the cell just right of or below a
+// window, and can be dragged to resize it
+fn resize_icon(
+    position: PhysicalPosition<f32>,
+    regions: &[WindowDrawDetails],
+    (font_width, font_height): (u64, u64),
+) -> Option<CursorIcon> {
+    let contains = |region: &Rect| {
+        position.x >= region.left
+            && position.x < region.right
+            && position.y >= region.top
+            && position.y < region.bottom
+    };
+
+    let top_window = regions
+        .iter()
+        .filter(|details| contains(&details.region))
+        .last()?;
+    if top_window.id != 1 {
+        return None;
+    }
+
+    let split_windows = regions
+        .iter()
+        .filter(|details| details.id != 1 && details.floating_order.is_none());
+    for details in split_windows {
+        let region = details.region;
+        let status_line = Rect::from_ltrb(
+            region.left,
+            region.bottom,
+            region.right,
+            region.bottom + font_height as f32,
+        );
+        let separator = Rect::from_ltrb(
+            region.right,
+            region.top,
+            region.right + font_width as f32,
+            region.bottom,
+        );
+
+        if contains(&status_line) {
+            return Some(CursorIcon::RowResize);
+        }
+        if contains(&separator) {
+            return Some(CursorIcon::ColResize);
+        }
+    }
+
+    None
+}
+
+// Links come from terminal output and buffer text, so anything that could name a local file or
+// program is refused rather than handed to the platform opener
+fn is_openable_url(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+        && !url.chars().any(|character| character.is_control())
+}
+
+// Hands the url to the platform's default handler, like a browser for web links
+fn open_url(url: &str) {
+    if !is_openable_url(url) {
+        error!(
+            "Refusing to open link {}: only http, https and mailto links are opened",
+            url
+        );
+        return;
+    }
+
+    #[cfg(target_os = "windows")]
+    let result = Command::new("rundll32")
+        .args(&["url.dll,FileProtocolHandler", url])
+        .spawn();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(url).spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("xdg-open").arg(url).spawn();
+
+    if let Err(error) = result {
+        error!("Could not open link {}: {}", url, error);
+    }
+}
+
 fn mouse_button_to_button_text(mouse_button: &MouseButton) -> Option<String> {
     match mouse_button {
         MouseButton::Left => Some("left".to_owned()),
@@ -82,6 +190,15 @@ pub struct MouseManager {
     touch_position: HashMap<(DeviceId, u64), TouchTrace>,
 
     window_details_under_mouse: Option<WindowDrawDetails>,
+    // Set while the left button is held after a ctrl+click opened a link, so neither the press nor
+    // the release reach neovim
+    link_click: bool,
+
+    // The pointer shows the busy icon, then a resize icon over separators, then the shape of the
+    // current mode
+    pub mode_shape: Option<MouseShape>,
+    pub busy: bool,
+    resize_icon: Option<CursorIcon>,
 
     mouse_hidden: bool,
     pub enabled: bool,
@@ -98,6 +215,10 @@ impl MouseManager {
             scroll_position: PhysicalPosition::new(0.0, 0.0),
             touch_position: HashMap::new(),
             window_details_under_mouse: None,
+            link_click: false,
+            mode_shape: None,
+            busy: false,
+            resize_icon: None,
             mouse_hidden: false,
             enabled: true,
         }
@@ -109,15 +230,31 @@ impl MouseManager {
         y: i32,
         keyboard_manager: &KeyboardManager,
         renderer: &Renderer,
-        windowed_context: &WindowedContext<PossiblyCurrent>,
+        window: &Window,
     ) {
-        let size = windowed_context.window().inner_size();
+        let size = window.inner_size();
         if x < 0 || x as u32 >= size.width || y < 0 || y as u32 >= size.height {
             return;
         }
 
         let position: PhysicalPosition<f32> = PhysicalPosition::new(x as f32, y as f32);
 
+        self.resize_icon = if self.enabled {
+            resize_icon(
+                position,
+                &renderer.window_regions,
+                renderer.grid_renderer.font_dimensions.into(),
+            )
+        } else {
+            None
+        };
+
+        // Presses over the tabline are handled by the renderer, unless a drag started in the grid
+        if self.dragging.is_none() && renderer.is_over_tabline(position.y) {
+            self.window_details_under_mouse = None;
+            return;
+        }
+
         // If dragging, the relevant window (the one which we send all commands to) is the one
         // which the mouse drag started on. Otherwise its the top rendered window
         let relevant_window_details = if self.dragging.is_some() {
@@ -190,6 +327,26 @@ impl MouseManager {
         }
     }
 
+    pub fn update_pointer_icon(&self, window: &Window) {
+        let icon = if self.busy {
+            Some(CursorIcon::Progress)
+        } else if self.resize_icon.is_some() {
+            self.resize_icon
+        } else {
+            self.mode_shape
+                .map(mouse_shape_to_icon)
+                .unwrap_or(Some(CursorIcon::Default))
+        };
+
+        match icon {
+            Some(icon) => {
+                window.set_cursor_icon(icon);
+                window.set_cursor_visible(!self.mouse_hidden);
+            }
+            None => window.set_cursor_visible(false),
+        }
+    }
+
     fn handle_pointer_transition(
         &mut self,
         mouse_button: &MouseButton,
@@ -313,7 +470,7 @@ impl MouseManager {
         &mut self,
         keyboard_manager: &KeyboardManager,
         renderer: &Renderer,
-        windowed_context: &WindowedContext<PossiblyCurrent>,
+        window: &Window,
         finger_id: (DeviceId, u64),
         location: PhysicalPosition<f32>,
         phase: &TouchPhase,
@@ -362,7 +519,7 @@ impl MouseManager {
                             location.y.round() as i32,
                             keyboard_manager,
                             renderer,
-                            windowed_context,
+                            window,
                         );
                     }
                     // the double check might seem useless, but the if branch above might set
@@ -385,7 +542,7 @@ impl MouseManager {
                         location.y.round() as i32,
                         keyboard_manager,
                         renderer,
-                        windowed_context,
+                        window,
                     );
                     self.handle_pointer_transition(&MouseButton::Left, true, keyboard_manager);
                 }
@@ -401,7 +558,7 @@ impl MouseManager {
                             trace.start.y.round() as i32,
                             keyboard_manager,
                             renderer,
-                            windowed_context,
+                            window,
                         );
                         self.handle_pointer_transition(&MouseButton::Left, true, keyboard_manager);
                         self.handle_pointer_transition(&MouseButton::Left, false, keyboard_manager);
@@ -416,7 +573,7 @@ impl MouseManager {
         event: &Event<()>,
         keyboard_manager: &KeyboardManager,
         renderer: &Renderer,
-        windowed_context: &WindowedContext<PossiblyCurrent>,
+        window: &Window,
     ) {
         match event {
             Event::WindowEvent {
@@ -428,12 +585,10 @@ impl MouseManager {
                     position.y as i32,
                     keyboard_manager,
                     renderer,
-                    windowed_context,
+                    window,
                 );
-                if self.mouse_hidden {
-                    windowed_context.window().set_cursor_visible(true);
-                    self.mouse_hidden = false;
-                }
+                self.mouse_hidden = false;
+                self.update_pointer_icon(window);
             }
             Event::WindowEvent {
                 event:
@@ -442,7 +597,9 @@ impl MouseManager {
                         ..
                     },
                 ..
-            } => self.handle_line_scroll(*x, *y, keyboard_manager),
+            } if !renderer.is_message_history_visible() => {
+                self.handle_line_scroll(*x, *y, keyboard_manager)
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::MouseWheel {
@@ -450,7 +607,7 @@ impl MouseManager {
                         ..
                     },
                 ..
-            } => self.handle_pixel_scroll(
+            } if !renderer.is_message_history_visible() => self.handle_pixel_scroll(
                 renderer.grid_renderer.font_dimensions.into(),
                 (delta.x as f32, delta.y as f32),
                 keyboard_manager,
@@ -468,11 +625,33 @@ impl MouseManager {
             } => self.handle_touch(
                 keyboard_manager,
                 renderer,
-                windowed_context,
+                window,
                 (*device_id, *id),
                 location.cast(),
                 phase,
             ),
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state,
+                        ..
+                    },
+                ..
+            } if self.link_click || keyboard_manager.is_ctrl_pressed() => {
+                if state == &ElementState::Released {
+                    if self.link_click {
+                        self.link_click = false;
+                    } else {
+                        self.handle_pointer_transition(&MouseButton::Left, false, keyboard_manager);
+                    }
+                } else if let Some(url) = renderer.hovered_link() {
+                    open_url(&url);
+                    self.link_click = true;
+                } else {
+                    self.handle_pointer_transition(&MouseButton::Left, true, keyboard_manager);
+                }
+            }
             Event::WindowEvent {
                 event: WindowEvent::MouseInput { button, state, .. },
                 ..
@@ -491,7 +670,7 @@ impl MouseManager {
                 if key_event.state == ElementState::Pressed {
                     let window_settings = SETTINGS.get::<WindowSettings>();
                     if window_settings.hide_mouse_when_typing && !self.mouse_hidden {
-                        windowed_context.window().set_cursor_visible(false);
+                        window.set_cursor_visible(false);
                         self.mouse_hidden = true;
                     }
                 }
@@ -500,3 +679,46 @@ impl MouseManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(id: u64, region: Rect, floating_order: Option<u64>) -> WindowDrawDetails {
+        WindowDrawDetails {
+            id,
+            region,
+            floating_order,
+        }
+    }
+
+    #[test]
+    fn test_is_openable_url() {
+        assert!(is_openable_url("https://neovide.dev"));
+        assert!(is_openable_url("HTTP://example.com/a?b=c"));
+        assert!(is_openable_url("mailto:someone@example.com"));
+        assert!(!is_openable_url("file:///home/user/evil.desktop"));
+        assert!(!is_openable_url("C:\\Users\\evil.lnk"));
+        assert!(!is_openable_url("javascript:alert(1)"));
+        assert!(!is_openable_url("https://example.com\nfile:///etc"));
+    }
+
+    #[test]
+    fn test_resize_icon() {
+        // Two side by side windows above the command line, with cells 10 by 20 pixels
+        let regions = vec![
+            details(1, Rect::from_wh(210.0, 120.0), None),
+            details(2, Rect::from_wh(100.0, 80.0), None),
+            details(3, Rect::from_xywh(110.0, 0.0, 100.0, 80.0), None),
+            details(4, Rect::from_xywh(120.0, 20.0, 50.0, 40.0), Some(1)),
+        ];
+        let icon_at = |x, y| resize_icon(PhysicalPosition::new(x, y), &regions, (10, 20));
+
+        assert_eq!(icon_at(105.0, 30.0), Some(CursorIcon::ColResize));
+        assert_eq!(icon_at(50.0, 90.0), Some(CursorIcon::RowResize));
+        assert_eq!(icon_at(150.0, 90.0), Some(CursorIcon::RowResize));
+        assert_eq!(icon_at(50.0, 30.0), None);
+        assert_eq!(icon_at(130.0, 30.0), None);
+        assert_eq!(icon_at(50.0, 110.0), None);
+    }
+}
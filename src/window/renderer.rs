@@ -1,10 +1,17 @@
 use std::convert::TryInto;
 
 use gl::types::*;
+use glutin::{
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder},
+    ContextBuilder, GlProfile,
+};
+use log::{info, warn};
 use skia_safe::{
     gpu::{gl::FramebufferInfo, BackendRenderTarget, DirectContext, SurfaceOrigin},
-    Canvas, ColorType, Surface,
+    AlphaType, Canvas, ColorType, ImageInfo, Surface,
 };
+use softbuffer::GraphicsContext;
 
 type WindowedContext = glutin::ContextWrapper<glutin::PossiblyCurrent, glutin::window::Window>;
 
@@ -42,14 +49,22 @@ fn create_surface(
     .expect("Could not create skia surface")
 }
 
-pub struct SkiaRenderer {
-    pub gr_context: DirectContext,
+fn create_raster_surface(window: &Window) -> Surface {
+    let size = window.inner_size();
+    // Minimized windows report a zero size, which skia refuses to allocate
+    let size = (size.width.max(1) as i32, size.height.max(1) as i32);
+    Surface::new_raster_n32_premul(size).expect("Could not create raster surface")
+}
+
+pub struct GlRenderer {
+    windowed_context: WindowedContext,
+    gr_context: DirectContext,
     fb_info: FramebufferInfo,
     surface: Surface,
 }
 
-impl SkiaRenderer {
-    pub fn new(windowed_context: &WindowedContext) -> SkiaRenderer {
+impl GlRenderer {
+    pub fn new(windowed_context: WindowedContext) -> GlRenderer {
         gl::load_with(|s| windowed_context.get_proc_address(s));
 
         let interface = skia_safe::gpu::gl::Interface::new_load_with(|name| {
@@ -71,20 +86,166 @@ impl SkiaRenderer {
                 format: skia_safe::gpu::gl::Format::RGBA8.into(),
             }
         };
-        let surface = create_surface(windowed_context, &mut gr_context, fb_info);
+        let surface = create_surface(&windowed_context, &mut gr_context, fb_info);
 
-        SkiaRenderer {
+        GlRenderer {
+            windowed_context,
             gr_context,
             fb_info,
             surface,
         }
     }
+}
+
+// Draws into a cpu raster surface and copies the pixels to the window, for machines where no
+// OpenGL context can be created
+pub struct SoftwareRenderer {
+    graphics_context: GraphicsContext<Window>,
+    surface: Surface,
+    // Kept between frames so presenting doesn't allocate
+    bytes: Vec<u8>,
+    pixels: Vec<u32>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(window: Window) -> SoftwareRenderer {
+        let surface = create_raster_surface(&window);
+        let graphics_context = unsafe { GraphicsContext::new(window) }
+            .unwrap_or_else(|_| panic!("Could not create software graphics context"));
+
+        SoftwareRenderer {
+            graphics_context,
+            surface,
+            bytes: Vec::new(),
+            pixels: Vec::new(),
+        }
+    }
+
+    fn present(&mut self) {
+        let (width, height) = (self.surface.width(), self.surface.height());
+        let image_info = ImageInfo::new(
+            (width, height),
+            ColorType::BGRA8888,
+            AlphaType::Premul,
+            None,
+        );
+        let row_bytes = width as usize * 4;
+        self.bytes.resize(row_bytes * height as usize, 0);
+        if !self
+            .surface
+            .read_pixels(&image_info, &mut self.bytes, row_bytes, (0, 0))
+        {
+            warn!("Could not read back the software rendered frame");
+            return;
+        }
+
+        // Softbuffer expects 0RGB pixels, which is BGRA read as a little endian u32 without the
+        // alpha channel
+        self.pixels.clear();
+        self.pixels.extend(
+            self.bytes
+                .chunks_exact(4)
+                .map(|pixel| u32::from_le_bytes([pixel[0], pixel[1], pixel[2], 0])),
+        );
+        self.graphics_context
+            .set_buffer(&self.pixels, width as u16, height as u16);
+    }
+}
+
+pub enum SkiaRenderer {
+    Gl(GlRenderer),
+    Software(SoftwareRenderer),
+}
+
+impl SkiaRenderer {
+    pub fn window(&self) -> &Window {
+        match self {
+            SkiaRenderer::Gl(renderer) => renderer.windowed_context.window(),
+            SkiaRenderer::Software(renderer) => renderer.graphics_context.window(),
+        }
+    }
 
     pub fn canvas(&mut self) -> &mut Canvas {
-        self.surface.canvas()
+        match self {
+            SkiaRenderer::Gl(renderer) => renderer.surface.canvas(),
+            SkiaRenderer::Software(renderer) => renderer.surface.canvas(),
+        }
     }
 
-    pub fn resize(&mut self, windowed_context: &WindowedContext) {
-        self.surface = create_surface(windowed_context, &mut self.gr_context, self.fb_info);
+    pub fn surface(&mut self) -> &mut Surface {
+        match self {
+            SkiaRenderer::Gl(renderer) => &mut renderer.surface,
+            SkiaRenderer::Software(renderer) => &mut renderer.surface,
+        }
     }
+
+    // Pushes the drawn frame to the window
+    pub fn present(&mut self) {
+        match self {
+            SkiaRenderer::Gl(renderer) => {
+                renderer.gr_context.flush(None);
+                renderer.windowed_context.swap_buffers().unwrap();
+            }
+            SkiaRenderer::Software(renderer) => renderer.present(),
+        }
+    }
+
+    pub fn resize(&mut self) {
+        match self {
+            SkiaRenderer::Gl(renderer) => {
+                renderer.surface = create_surface(
+                    &renderer.windowed_context,
+                    &mut renderer.gr_context,
+                    renderer.fb_info,
+                );
+            }
+            SkiaRenderer::Software(renderer) => {
+                renderer.surface = create_raster_surface(renderer.graphics_context.window());
+            }
+        }
+    }
+}
+
+// Creates the window together with the renderer drawing into it. Falls back to software rendering
+// when no OpenGL context can be created, or when it is forced with --software-render.
+pub fn create_skia_renderer(
+    window_builder: WindowBuilder,
+    event_loop: &EventLoop<()>,
+    srgb: bool,
+    force_software: bool,
+) -> SkiaRenderer {
+    if !force_software {
+        match create_gl_context(window_builder.clone(), event_loop, srgb) {
+            Ok(windowed_context) => {
+                info!("Using the OpenGL rendering backend");
+                return SkiaRenderer::Gl(GlRenderer::new(windowed_context));
+            }
+            Err(error) => warn!(
+                "Could not create an OpenGL context, falling back to software rendering: {}",
+                error
+            ),
+        }
+    }
+
+    info!("Using the software rendering backend");
+    let window = window_builder
+        .build(event_loop)
+        .expect("Could not create window");
+    SkiaRenderer::Software(SoftwareRenderer::new(window))
+}
+
+fn create_gl_context(
+    window_builder: WindowBuilder,
+    event_loop: &EventLoop<()>,
+    srgb: bool,
+) -> Result<WindowedContext, String> {
+    let windowed_context = ContextBuilder::new()
+        .with_pixel_format(24, 8)
+        .with_stencil_buffer(8)
+        .with_gl_profile(GlProfile::Core)
+        .with_vsync(false)
+        .with_srgb(srgb)
+        .build_windowed(window_builder, event_loop)
+        .map_err(|error| error.to_string())?;
+    unsafe { windowed_context.make_current() }.map_err(|(_, error)| error.to_string())
 }
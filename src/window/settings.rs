@@ -2,9 +2,13 @@ use crate::{cmd_line::CmdLineSettings, settings::*};
 
 #[derive(Clone, SettingGroup)]
 pub struct WindowSettings {
+    #[setting(min = 1)]
     pub refresh_rate: u64,
     pub no_idle: bool,
+    #[setting(min = 0.0, max = 1.0, clamp)]
     pub transparency: f32,
+    #[setting(min = 0.1, max = 10.0, clamp)]
+    pub scale_factor: f32,
     pub fullscreen: bool,
     pub iso_layout: bool,
     pub remember_window_size: bool,
@@ -18,6 +22,7 @@ impl Default for WindowSettings {
     fn default() -> Self {
         Self {
             transparency: 1.0,
+            scale_factor: 1.0,
             fullscreen: false,
             iso_layout: false,
             refresh_rate: 60,